@@ -0,0 +1,227 @@
+use std::collections::VecDeque;
+
+use nalgebra::Vector2;
+
+use crate::{game_event::GameEvent, gameboard::LineClear};
+
+// Durations in seconds
+const CLEAR_FLASH_TIME: f32 = 0.3;
+const LOCK_FLASH_TIME: f32 = 0.12;
+const BANNER_TIME: f32 = 1.5;
+// Older clear banners waiting to be shown are dropped past this count
+const MAX_BANNERS: usize = 3;
+
+const T_SPIN_NAMES: [&str; 4] = ["T-SPIN", "T-SPIN SINGLE", "T-SPIN DOUBLE", "T-SPIN TRIPLE"];
+
+// Short lived effects built from the board events and played on the renderer clock
+pub struct Animations {
+    // Board rows that were cleared, with the time left to flash them
+    cleared_rows: Option<(Vec<usize>, f32)>,
+    locked_squares: Option<(Vec<Vector2<isize>>, f32)>,
+    // Clear banners shown one after the other, the first one with its time left
+    banners: VecDeque<(String, f32)>,
+    // Time left of the level up banner, shown once no clear banner is left
+    level_up: Option<f32>,
+    // Something started or ended since the last draw
+    changed: bool
+}
+
+impl Animations {
+
+    pub fn new() -> Self {
+        Animations {
+            cleared_rows: None,
+            locked_squares: None,
+            banners: VecDeque::new(),
+            level_up: None,
+            changed: false
+        }
+    }
+
+
+    pub fn record(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Locked(piece) => {
+                let positions = piece.get_squares().iter().map(|square| square.get_position()).collect();
+                self.locked_squares = Some((positions, LOCK_FLASH_TIME));
+            },
+            GameEvent::LinesCleared { rows, kind } => {
+                if !rows.is_empty() {
+                    self.cleared_rows = Some((rows.clone(), CLEAR_FLASH_TIME));
+                }
+                if let Some(banner) = get_clear_banner(kind) {
+                    self.banners.push_back((banner, BANNER_TIME));
+                    // The banner on screen is kept, the oldest waiting one goes
+                    if self.banners.len() > MAX_BANNERS {
+                        self.banners.remove(1);
+                    }
+                }
+            },
+            // A new level replaces the pending level up instead of queueing behind it
+            GameEvent::LevelUp(_) => self.level_up = Some(BANNER_TIME),
+            _ => return
+        }
+        self.changed = true;
+    }
+
+
+    pub fn advance_time(&mut self, time_delta: f32) {
+        let mut ended = false;
+        if let Some((_, time_left)) = self.cleared_rows.as_mut() {
+            *time_left -= time_delta;
+            ended |= *time_left <= 0.0;
+        }
+        if let Some((_, time_left)) = self.locked_squares.as_mut() {
+            *time_left -= time_delta;
+            ended |= *time_left <= 0.0;
+        }
+        if let Some(time_left) = self.banners.front_mut().map(|(_, time_left)| time_left).or(self.level_up.as_mut()) {
+            *time_left -= time_delta;
+            ended |= *time_left <= 0.0;
+        }
+
+        if ended {
+            self.cleared_rows = self.cleared_rows.take().filter(|(_, time_left)| *time_left > 0.0);
+            self.locked_squares = self.locked_squares.take().filter(|(_, time_left)| *time_left > 0.0);
+            if self.banners.front().is_some_and(|(_, time_left)| *time_left <= 0.0) {
+                self.banners.pop_front();
+            }
+            self.level_up = self.level_up.filter(|time_left| *time_left > 0.0);
+            self.changed = true;
+        }
+    }
+
+
+    pub fn has_changed(&self) -> bool {
+        self.changed
+    }
+
+
+    pub fn set_drawn(&mut self) {
+        self.changed = false;
+    }
+
+
+    pub fn get_cleared_rows(&self) -> &[usize] {
+        self.cleared_rows.as_ref().map_or(&[], |(rows, _)| rows)
+    }
+
+
+    pub fn get_locked_squares(&self) -> &[Vector2<isize>] {
+        self.locked_squares.as_ref().map_or(&[], |(positions, _)| positions)
+    }
+
+
+    pub fn get_banner(&self) -> Option<&str> {
+        match self.banners.front() {
+            Some((banner, _)) => Some(banner.as_str()),
+            None => self.level_up.map(|_| "LEVEL UP")
+        }
+    }
+}
+
+
+// Only clears worth noticing get a banner
+fn get_clear_banner(clear: &LineClear) -> Option<String> {
+    let mut names = vec![];
+    if clear.back_to_back {
+        names.push("B2B");
+    }
+    if clear.t_spin {
+        names.push(T_SPIN_NAMES[usize::min(clear.lines as usize, 3)]);
+    } else if clear.lines == 4 {
+        names.push("TETRIS");
+    } else if clear.lines >= 5 {
+        // Only pentominoes clear 5 lines at once
+        names.push("PENTRIS");
+    }
+    if clear.perfect_clear {
+        names.push("PERFECT CLEAR");
+    }
+    (!names.is_empty()).then(|| names.join(" "))
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{game_event::GameEvent, gameboard::LineClear};
+
+    use super::{Animations, BANNER_TIME, CLEAR_FLASH_TIME};
+
+    fn clear(lines: u32, t_spin: bool, perfect_clear: bool, back_to_back: bool) -> GameEvent {
+        GameEvent::LinesCleared {
+            rows: (0..lines as usize).collect(),
+            kind: LineClear { lines, t_spin, perfect_clear, back_to_back }
+        }
+    }
+
+    #[test]
+    fn notable_clears_are_announced_with_back_to_back() {
+        let mut sut = Animations::new();
+
+        sut.record(&clear(4, false, false, false));
+        sut.record(&clear(2, true, true, true));
+        sut.record(&clear(1, false, false, false));
+        sut.record(&GameEvent::LevelUp(2));
+
+        let mut banners = vec![];
+        while let Some(banner) = sut.get_banner() {
+            banners.push(banner.to_string());
+            sut.advance_time(BANNER_TIME);
+        }
+        assert_eq!(banners, vec!["TETRIS", "B2B T-SPIN DOUBLE PERFECT CLEAR", "LEVEL UP"]);
+    }
+
+
+    #[test]
+    fn clears_of_five_lines_have_their_own_banner() {
+        let mut sut = Animations::new();
+
+        sut.record(&clear(5, false, false, false));
+        sut.record(&clear(4, false, false, true));
+
+        let mut banners = vec![];
+        while let Some(banner) = sut.get_banner() {
+            banners.push(banner.to_string());
+            sut.advance_time(BANNER_TIME);
+        }
+        assert_eq!(banners, vec!["PENTRIS", "B2B TETRIS"]);
+    }
+
+
+    #[test]
+    fn level_ups_are_shown_once_after_the_clears() {
+        let mut sut = Animations::new();
+
+        for level in 2..50 {
+            sut.record(&GameEvent::LevelUp(level));
+        }
+        for tetris in 0..5 {
+            sut.record(&clear(4, false, false, tetris > 0));
+        }
+
+        let mut banners = vec![];
+        while let Some(banner) = sut.get_banner() {
+            banners.push(banner.to_string());
+            sut.advance_time(BANNER_TIME);
+        }
+        assert_eq!(banners, vec!["TETRIS", "B2B TETRIS", "B2B TETRIS", "LEVEL UP"]);
+    }
+
+
+    #[test]
+    fn cleared_rows_flash_until_their_time_is_over() {
+        let mut sut = Animations::new();
+
+        sut.record(&clear(2, false, false, false));
+        assert_eq!(sut.get_cleared_rows(), &[0, 1]);
+        assert!(sut.has_changed());
+        sut.set_drawn();
+
+        sut.advance_time(CLEAR_FLASH_TIME / 2.0);
+        assert!(!sut.has_changed());
+        sut.advance_time(CLEAR_FLASH_TIME);
+        assert!(sut.get_cleared_rows().is_empty());
+        assert!(sut.has_changed());
+    }
+}
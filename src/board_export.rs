@@ -0,0 +1,218 @@
+use std::{fmt::Display, fs, path::Path};
+
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::{cell_grid::{CellGrid, Cell}, gameboard::GameBoard, piece::Color, replay::Replay, scene::rgb_of, game::STEPS_PER_SECOND};
+
+// Side of a drawn cell in pixels, big boards drawing each logical cell larger
+const CELL_PIXELS: usize = 20;
+const EMPTY_COLOR: &str = "#18182a";
+const GRID_COLOR: &str = "#2a2a3c";
+const GHOST_OPACITY: f32 = 0.35;
+
+#[derive(Debug)]
+pub struct ExportError(String);
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+
+#[derive(EnumIter, Copy, Clone, PartialEq, Debug)]
+pub enum ExportFormat {
+    Svg,
+    Html
+}
+
+impl ExportFormat {
+
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Svg => "svg",
+            ExportFormat::Html => "html"
+        }
+    }
+
+
+    pub fn from_path(path: &Path) -> Option<ExportFormat> {
+        let extension = path.extension()?.to_str()?;
+        ExportFormat::iter().find(|format| format.get_extension() == extension)
+    }
+}
+
+
+fn css_color(color: Color) -> String {
+    let [red, green, blue] = rgb_of(color).map(|component| (component * 255.0).round() as u8);
+    format!("#{:02x}{:02x}{:02x}", red, green, blue)
+}
+
+
+// Cells are colored through classes named after their color, "fill" for SVG and "background" for HTML
+fn style(property: &str, frames: usize, frame_seconds: f32) -> String {
+    let mut style = format!(".empty {{ {}: {} }}\n.ghost {{ opacity: {} }}\n", property, EMPTY_COLOR, GHOST_OPACITY);
+    for color in Color::iter() {
+        style.push_str(&format!(".{} {{ {}: {} }}\n", color.get_name(), property, css_color(color)));
+    }
+
+    // Frames of a replay are shown one after the other, each for its share of the loop
+    if frames > 1 {
+        let share = 100.0 / frames as f32;
+        style.push_str(&format!("@keyframes frame {{ 0% {{ visibility: visible }} {}% {{ visibility: hidden }} 100% {{ visibility: hidden }} }}\n", share));
+        style.push_str(&format!(".frame {{ visibility: hidden; animation: frame {}s step-end infinite }}\n", frames as f32 * frame_seconds));
+        for frame in 0..frames {
+            style.push_str(&format!("#frame-{} {{ animation-delay: {}s }}\n", frame, frame as f32 * frame_seconds));
+        }
+    }
+    style
+}
+
+
+fn cell_class(cell: &Cell) -> String {
+    match cell {
+        Cell::Empty => String::from("empty"),
+        Cell::Ghost(color) => format!("ghost {}", color.get_name()),
+        Cell::Block(color, _) => color.get_name().to_string()
+    }
+}
+
+
+fn to_svg(grids: &[CellGrid], cell_pixels: usize, frame_seconds: f32) -> String {
+    let rows = grids.first().map_or(0, |grid| grid.get_rows().len());
+    let columns = grids.first().and_then(|grid| grid.get_rows().first()).map_or(0, Vec::len);
+    let (width, height) = (columns * cell_pixels, rows * cell_pixels);
+
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n", width, height, width, height);
+    svg.push_str(&format!("<style>\n{}</style>\n", style("fill", grids.len(), frame_seconds)));
+    svg.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>\n", width, height, GRID_COLOR));
+    for (index, grid) in grids.iter().enumerate() {
+        svg.push_str(&format!("<g class=\"frame\" id=\"frame-{}\">\n", index));
+        for (y, row) in grid.get_rows().iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                // A pixel is left between cells for the grid
+                svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" class=\"{}\"/>\n",
+                    x * cell_pixels + 1, y * cell_pixels + 1, cell_pixels - 2, cell_pixels - 2, cell_class(cell)));
+            }
+        }
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+
+fn to_html(grids: &[CellGrid], cell_pixels: usize, frame_seconds: f32) -> String {
+    let columns = grids.first().and_then(|grid| grid.get_rows().first()).map_or(0, Vec::len);
+
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Tetrust</title>\n<style>\n");
+    html.push_str(&format!(".board {{ display: grid; background: {}; width: fit-content }}\n", GRID_COLOR));
+    html.push_str(&format!(".frame {{ grid-area: 1 / 1; display: grid; grid-template-columns: repeat({}, {}px); grid-auto-rows: {}px; gap: 2px; padding: 1px }}\n",
+        columns, cell_pixels - 2, cell_pixels - 2));
+    html.push_str(&style("background", grids.len(), frame_seconds));
+    html.push_str("</style>\n</head>\n<body>\n<div class=\"board\">\n");
+    for (index, grid) in grids.iter().enumerate() {
+        html.push_str(&format!("<div class=\"frame\" id=\"frame-{}\">\n", index));
+        for row in grid.get_rows() {
+            for cell in row {
+                html.push_str(&format!("<div class=\"{}\"></div>", cell_class(cell)));
+            }
+            html.push('\n');
+        }
+        html.push_str("</div>\n");
+    }
+    html.push_str("</div>\n</body>\n</html>\n");
+    html
+}
+
+
+// Every grid is a frame of an animation looping through them, a single grid giving a still picture
+pub fn export_grids(grids: &[CellGrid], format: ExportFormat, cell_pixels: usize, frame_seconds: f32) -> String {
+    match format {
+        ExportFormat::Svg => to_svg(grids, cell_pixels, frame_seconds),
+        ExportFormat::Html => to_html(grids, cell_pixels, frame_seconds)
+    }
+}
+
+
+pub fn export_board(board: &GameBoard, format: ExportFormat) -> String {
+    let cell_pixels = CELL_PIXELS * board.get_dimensions().cell_size;
+    export_grids(&[CellGrid::of_board(board)], format, cell_pixels, 0.0)
+}
+
+
+// The board every given number of frames, played at the speed of the game
+pub fn export_replay(replay: &Replay, every: u32, format: ExportFormat) -> String {
+    let mut grids = vec![];
+    let game = replay.play_with(None, |game| {
+        if game.get_frame().is_multiple_of(every) {
+            grids.push(CellGrid::of_board(game.get_board()));
+        }
+    });
+    let cell_pixels = CELL_PIXELS * game.get_board().get_dimensions().cell_size;
+    export_grids(&grids, format, cell_pixels, every as f32 / STEPS_PER_SECOND as f32)
+}
+
+
+// A single frame of the replay when one is given, the whole replay otherwise
+pub fn export_file(replay: &Path, frame: Option<u32>, every: u32, output: &Path) -> Result<(), ExportError> {
+    let format = ExportFormat::from_path(output)
+        .ok_or(ExportError(format!("Expected a .svg or .html file, found : {}", output.display())))?;
+    let replay = Replay::load(replay).map_err(|error| ExportError(error.to_string()))?;
+    let text = match frame {
+        Some(frame) => export_board(replay.play(Some(frame)).get_board(), format),
+        None => export_replay(&replay, every, format)
+    };
+    fs::write(output, text).map_err(|error| ExportError(format!("Cannot write {} : {}", output.display(), error)))
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{gameboard::GameBoard, game_mode::{ModeKind, ModeOptions}, replay::Replay};
+
+    use super::{export_board, export_replay, ExportFormat};
+
+    #[test]
+    fn format_is_chosen_by_extension() {
+        assert_eq!(ExportFormat::from_path(Path::new("board.svg")), Some(ExportFormat::Svg));
+        assert_eq!(ExportFormat::from_path(Path::new("docs/board.html")), Some(ExportFormat::Html));
+        assert_eq!(ExportFormat::from_path(Path::new("board.png")), None);
+    }
+
+
+    #[test]
+    fn every_cell_of_the_board_is_exported_with_its_color_class() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let board = GameBoard::new(rng, 1);
+        let dimensions = board.get_dimensions();
+        let color = board.get_current_piece().get_squares()[0].get_color().get_name();
+
+        let svg = export_board(&board, ExportFormat::Svg);
+        let html = export_board(&board, ExportFormat::Html);
+
+        assert_eq!(svg.matches("<rect x=").count(), dimensions.width * dimensions.height);
+        assert_eq!(svg.matches(&format!("class=\"{}\"", color)).count(), 4);
+        assert_eq!(html.matches(&format!("class=\"ghost {}\"", color)).count(), 4);
+        assert!(!html.contains("@keyframes"));
+    }
+
+
+    #[test]
+    fn replays_are_animated_frame_by_frame() {
+        let mut replay = Replay::new(3, ModeKind::Marathon, ModeOptions::default());
+        replay.set_frames(120);
+
+        let sut = export_replay(&replay, 60, ExportFormat::Svg);
+
+        assert_eq!(sut.matches("<g class=\"frame\"").count(), 3);
+        assert!(sut.contains("@keyframes"));
+        assert!(sut.contains("#frame-2 { animation-delay: 1s }"));
+    }
+}
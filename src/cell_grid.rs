@@ -0,0 +1,80 @@
+use crate::{gameboard::GameBoard, piece::Color, piece_factory::PieceType};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Cell {
+    Empty,
+    // Where the current piece would land
+    Ghost(Color),
+    // The piece type is kept for renderers marking pieces with their letter
+    Block(Color, Option<PieceType>)
+}
+
+// What every cell of the well shows, the top row first, shared by the text based renderers
+#[derive(Clone, PartialEq, Debug)]
+pub struct CellGrid {
+    rows: Vec<Vec<Cell>>
+}
+
+impl CellGrid {
+
+    // Hidden squares are left empty and the current piece is drawn over its ghost
+    pub fn of_board(board: &GameBoard) -> CellGrid {
+        let dimensions = board.get_dimensions();
+        let mut rows = vec![vec![Cell::Empty; dimensions.width]; dimensions.height];
+        let mut set = |x: isize, y: isize, cell: Cell| {
+            if (0..dimensions.width as isize).contains(&x) && (0..dimensions.height as isize).contains(&y) {
+                rows[dimensions.height - 1 - y as usize][x as usize] = cell;
+            }
+        };
+
+        for column in board.get_square_board() {
+            for square in column.iter().flatten().filter(|square| board.is_square_shown(square)) {
+                let position = square.get_position();
+                set(position.x, position.y, Cell::Block(square.get_color(), square.get_piece_type()));
+            }
+        }
+        for square in board.get_ghost_piece().get_squares() {
+            let position = square.get_position();
+            set(position.x, position.y, Cell::Ghost(square.get_color()));
+        }
+        for square in board.get_current_piece().get_squares() {
+            let position = square.get_position();
+            set(position.x, position.y, Cell::Block(square.get_color(), square.get_piece_type()));
+        }
+        CellGrid { rows }
+    }
+
+
+    pub fn get_rows(&self) -> &Vec<Vec<Cell>> {
+        &self.rows
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::gameboard::{GameBoard, MovementDirection};
+
+    use super::{CellGrid, Cell};
+
+    #[test]
+    fn grid_shows_the_piece_over_its_ghost_and_locked_squares() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let mut board = GameBoard::new(rng, 1);
+        let color = board.get_current_piece().get_squares()[0].get_color();
+        let piece_type = board.get_current_piece().get_piece_type();
+        let _ = board.try_move(MovementDirection::Top);
+        board.lock_current_piece();
+
+        let sut = CellGrid::of_board(&board);
+
+        let count = |wanted: fn(&Cell) -> bool| sut.get_rows().iter().flatten().filter(|cell| wanted(cell)).count();
+        assert_eq!(sut.get_rows().len(), board.get_dimensions().height);
+        assert!(sut.get_rows().last().unwrap().contains(&Cell::Block(color, Some(piece_type))));
+        assert_eq!(count(|cell| matches!(cell, Cell::Block(..))), 8);
+        assert_eq!(count(|cell| matches!(cell, Cell::Ghost(_))), 4);
+    }
+}
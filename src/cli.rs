@@ -0,0 +1,348 @@
+use std::fmt::Display;
+
+use std::path::{Path, PathBuf};
+
+use crate::{game_mode::{ModeKind, ModeOptions}, gameboard::BlockVisibility, puzzle_mode::Puzzle, piece_factory::PieceSet, scoring::ScoringKind, graphics::{RendererKind, DisplayOptions}, gif_export::GifOptions, theme::{Theme, Palette}};
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub mode: ModeKind,
+    pub mode_options: ModeOptions,
+    pub renderer: RendererKind,
+    pub display_options: DisplayOptions,
+    // Replay file written once the game is over
+    pub record: Option<PathBuf>
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            mode: ModeKind::Marathon,
+            mode_options: ModeOptions::default(),
+            renderer: RendererKind::Ascii,
+            display_options: DisplayOptions::default(),
+            record: None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Command {
+    Play(Box<Options>),
+    // Board of a replay written to a PNG file
+    Snapshot { replay: PathBuf, frame: Option<u32>, output: PathBuf },
+    // Board of a replay at a frame, or the whole replay, written to an SVG or HTML file
+    Export { replay: PathBuf, frame: Option<u32>, every: u32, output: PathBuf },
+    // Replay written to an animated GIF file
+    Gif { replay: PathBuf, options: GifOptions }
+}
+
+#[derive(Debug)]
+pub struct ArgumentError(String);
+
+impl Display for ArgumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+
+// "snapshot <replay> [--frame <n>] [--output <file.png>]",
+// "export <replay> [--frame <n>] [--every <frames>] [--output <file.svg|file.html>]",
+// "gif <replay> [--every <frames>] [--speed <factor>] [--from <frame>] [--to <frame>] [--scale <pixels>] [--output <file.gif>]"
+// or the options of a game
+pub fn parse_command<I: Iterator<Item = String>>(args: I) -> Result<Command, ArgumentError> {
+    let mut args = args.peekable();
+    match args.next_if(|argument| ["snapshot", "export", "gif"].contains(&argument.as_str())).as_deref() {
+        Some("snapshot") => parse_snapshot(args),
+        Some("export") => parse_export(args),
+        Some("gif") => parse_gif(args),
+        _ => parse(args).map(|options| Command::Play(Box::new(options)))
+    }
+}
+
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, ArgumentError> {
+    let value = value.ok_or(ArgumentError(format!("{} expects a number", flag)))?;
+    value.parse::<T>().map_err(|_| ArgumentError(format!("Invalid number for {} : {}", flag, value)))
+}
+
+
+fn parse_snapshot<I: Iterator<Item = String>>(mut args: I) -> Result<Command, ArgumentError> {
+    let replay = args.next().ok_or(ArgumentError(String::from("snapshot expects a replay file")))?;
+    let mut frame = None;
+    let mut output = PathBuf::from("snapshot.png");
+    while let Some(argument) = args.next() {
+        match argument.as_str() {
+            "--frame" => frame = Some(parse_number("--frame", args.next())?),
+            "--output" => {
+                output = PathBuf::from(args.next().ok_or(ArgumentError(String::from("--output expects a file")))?);
+            },
+            _ => return Err(ArgumentError(format!("Unknown argument : {}", argument)))
+        }
+    }
+    Ok(Command::Snapshot { replay: PathBuf::from(replay), frame, output })
+}
+
+
+fn parse_export<I: Iterator<Item = String>>(mut args: I) -> Result<Command, ArgumentError> {
+    let replay = args.next().ok_or(ArgumentError(String::from("export expects a replay file")))?;
+    let mut frame = None;
+    let mut every = 12;
+    let mut output = PathBuf::from("board.svg");
+    while let Some(argument) = args.next() {
+        match argument.as_str() {
+            "--frame" => frame = Some(parse_number("--frame", args.next())?),
+            "--every" => every = parse_number("--every", args.next())?,
+            "--output" => {
+                output = PathBuf::from(args.next().ok_or(ArgumentError(String::from("--output expects a file")))?);
+            },
+            _ => return Err(ArgumentError(format!("Unknown argument : {}", argument)))
+        }
+    }
+
+    if every == 0 {
+        return Err(ArgumentError(String::from("--every must be positive")));
+    }
+    Ok(Command::Export { replay: PathBuf::from(replay), frame, every, output })
+}
+
+
+fn parse_gif<I: Iterator<Item = String>>(mut args: I) -> Result<Command, ArgumentError> {
+    let replay = args.next().ok_or(ArgumentError(String::from("gif expects a replay file")))?;
+    let mut options = GifOptions::default();
+    while let Some(argument) = args.next() {
+        match argument.as_str() {
+            "--every" => options.every = parse_number("--every", args.next())?,
+            "--speed" => options.speed = parse_number("--speed", args.next())?,
+            "--from" => options.from_frame = parse_number("--from", args.next())?,
+            "--to" => options.to_frame = Some(parse_number("--to", args.next())?),
+            "--scale" => options.cell_pixels = parse_number("--scale", args.next())?,
+            "--output" => {
+                options.output = PathBuf::from(args.next().ok_or(ArgumentError(String::from("--output expects a file")))?);
+            },
+            _ => return Err(ArgumentError(format!("Unknown argument : {}", argument)))
+        }
+    }
+
+    if options.every == 0 || options.cell_pixels == 0 || options.speed <= 0.0 {
+        return Err(ArgumentError(String::from("--every, --speed and --scale must be positive")));
+    }
+    if options.to_frame.is_some_and(|to_frame| to_frame < options.from_frame) {
+        return Err(ArgumentError(String::from("--to must not come before --from")));
+    }
+    if !options.has_valid_delay() {
+        return Err(ArgumentError(String::from("GIF pictures must be at least 1/100 s apart, raise --every or lower --speed")));
+    }
+    Ok(Command::Gif { replay: PathBuf::from(replay), options })
+}
+
+
+pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, ArgumentError> {
+    let mut options = Options::default();
+    while let Some(argument) = args.next() {
+        match argument.as_str() {
+            "--mode" => {
+                let name = args.next().ok_or(ArgumentError(String::from("--mode expects a mode name")))?;
+                options.mode = ModeKind::from_name(&name)
+                    .ok_or(ArgumentError(format!("Unknown mode : {}", name)))?;
+            },
+            "--infinite-hold" => options.mode_options.infinite_hold = true,
+            "--puzzle" => {
+                let path = args.next().ok_or(ArgumentError(String::from("--puzzle expects a file")))?;
+                let puzzle = Puzzle::load(Path::new(&path)).map_err(|error| ArgumentError(error.to_string()))?;
+                options.mode = ModeKind::Puzzle;
+                options.mode_options.puzzle = Some(puzzle);
+            },
+            "--pieces" => {
+                let path = args.next().ok_or(ArgumentError(String::from("--pieces expects a file")))?;
+                let piece_set = PieceSet::load(Path::new(&path)).map_err(|error| ArgumentError(error.to_string()))?;
+                options.mode_options.piece_set = Some(piece_set);
+            },
+            "--scoring" => {
+                let name = args.next().ok_or(ArgumentError(String::from("--scoring expects a scoring name")))?;
+                options.mode_options.scoring = Some(ScoringKind::from_name(&name)
+                    .ok_or(ArgumentError(format!("Unknown scoring : {}", name)))?);
+            },
+            "--renderer" => {
+                let name = args.next().ok_or(ArgumentError(String::from("--renderer expects ascii or 3d")))?;
+                options.renderer = RendererKind::from_name(&name)
+                    .ok_or(ArgumentError(format!("Unknown renderer : {}", name)))?;
+                if !options.renderer.is_available() {
+                    return Err(ArgumentError(format!("The {} renderer is not part of this build", name)));
+                }
+            },
+            "--theme" => {
+                let name = args.next().ok_or(ArgumentError(String::from("--theme expects a theme name or file")))?;
+                options.display_options.theme = match Theme::from_name(&name) {
+                    Some(theme) => theme,
+                    None => Theme::load(Path::new(&name)).map_err(|error| ArgumentError(error.to_string()))?
+                };
+            },
+            "--palette" => {
+                let name = args.next().ok_or(ArgumentError(String::from("--palette expects deuteranopia, protanopia or tritanopia")))?;
+                options.display_options.palette = Some(Palette::from_name(&name)
+                    .ok_or(ArgumentError(format!("Unknown palette : {}", name)))?);
+            },
+            "--piece-letters" => options.display_options.piece_letters = true,
+            "--record" => {
+                let path = args.next().ok_or(ArgumentError(String::from("--record expects a file")))?;
+                options.record = Some(PathBuf::from(path));
+            },
+            "--blocks" => {
+                let name = args.next().ok_or(ArgumentError(String::from("--blocks expects a visibility")))?;
+                options.mode_options.visibility = BlockVisibility::from_name(&name)
+                    .ok_or(ArgumentError(format!("Unknown block visibility : {}", name)))?;
+            },
+            _ => return Err(ArgumentError(format!("Unknown argument : {}", argument)))
+        }
+    }
+
+    if options.mode == ModeKind::Puzzle && options.mode_options.puzzle.is_none() {
+        return Err(ArgumentError(String::from("The puzzle mode expects a --puzzle file")));
+    }
+    Ok(options)
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{game_mode::ModeKind, gameboard::BlockVisibility, scoring::ScoringKind, graphics::RendererKind, theme::{Theme, Palette}};
+
+    use super::{parse, parse_command, Command};
+
+    fn to_args(line: &str) -> impl Iterator<Item = String> + '_ {
+        line.split_whitespace().map(String::from)
+    }
+
+    #[test]
+    fn no_argument_starts_a_marathon() {
+        let result = parse(to_args("")).unwrap();
+
+        assert_eq!(result.mode, ModeKind::Marathon);
+        assert!(!result.mode_options.infinite_hold);
+    }
+
+
+    #[test]
+    fn mode_and_flags_are_parsed() {
+        let result = parse(to_args("--mode zen --infinite-hold")).unwrap();
+
+        assert_eq!(result.mode, ModeKind::Zen);
+        assert!(result.mode_options.infinite_hold);
+    }
+
+
+    #[test]
+    fn unknown_mode_is_an_error() {
+        assert!(parse(to_args("--mode sprint-to-the-moon")).is_err());
+    }
+
+
+    #[test]
+    fn fading_blocks_take_a_duration() {
+        let result = parse(to_args("--blocks fading:3.5")).unwrap();
+
+        assert_eq!(result.mode_options.visibility, BlockVisibility::Fading(3.5));
+        assert!(parse(to_args("--blocks fading:soon")).is_err());
+    }
+
+
+    #[test]
+    fn puzzle_file_selects_the_puzzle_mode() {
+        let result = parse(to_args("--puzzle puzzles/t_spin_double.txt")).unwrap();
+
+        assert_eq!(result.mode, ModeKind::Puzzle);
+        assert!(result.mode_options.puzzle.is_some());
+        assert!(parse(to_args("--mode puzzle")).is_err());
+    }
+
+
+    #[test]
+    fn piece_set_file_is_loaded() {
+        let result = parse(to_args("--pieces pieces/trominoes.txt")).unwrap();
+
+        assert!(result.mode_options.piece_set.is_some());
+        assert!(parse(to_args("--pieces pieces/missing.txt")).is_err());
+    }
+
+
+    #[test]
+    fn scoring_overrides_the_one_of_the_mode() {
+        let result = parse(to_args("--scoring nes")).unwrap();
+
+        assert_eq!(result.mode_options.scoring, Some(ScoringKind::Nes));
+        assert!(parse(to_args("--scoring tetris-99")).is_err());
+    }
+
+
+    #[test]
+    fn renderer_is_selected_by_name() {
+        assert_eq!(parse(to_args("")).unwrap().renderer, RendererKind::Ascii);
+        assert!(parse(to_args("--renderer 2.5d")).is_err());
+        assert_eq!(parse(to_args("--renderer 3d")).is_ok(), cfg!(feature = "3d"));
+    }
+
+
+    #[test]
+    fn snapshot_subcommand_takes_a_replay_and_a_frame() {
+        match parse_command(to_args("snapshot game.replay --frame 300")).unwrap() {
+            Command::Snapshot { replay, frame, output } => {
+                assert_eq!(replay.to_str(), Some("game.replay"));
+                assert_eq!(frame, Some(300));
+                assert_eq!(output.to_str(), Some("snapshot.png"));
+            },
+            command => panic!("Expected a snapshot, found {:?}", command)
+        }
+        assert!(parse_command(to_args("snapshot")).is_err());
+        assert!(matches!(parse_command(to_args("--record game.replay")).unwrap(), Command::Play(options) if options.record.is_some()));
+    }
+
+
+    #[test]
+    fn gif_subcommand_checks_its_options() {
+        match parse_command(to_args("gif game.replay --every 6 --speed 2 --from 60 --to 600 --scale 8")).unwrap() {
+            Command::Gif { replay, options } => {
+                assert_eq!(replay.to_str(), Some("game.replay"));
+                assert_eq!((options.every, options.speed, options.from_frame, options.to_frame), (6, 2.0, 60, Some(600)));
+                assert_eq!(options.cell_pixels, 8);
+            },
+            command => panic!("Expected a gif, found {:?}", command)
+        }
+        assert!(parse_command(to_args("gif game.replay --every 0")).is_err());
+        assert!(parse_command(to_args("gif game.replay --from 60 --to 30")).is_err());
+        assert!(parse_command(to_args("gif game.replay --every 1")).is_err());
+    }
+
+
+    #[test]
+    fn export_subcommand_defaults_to_the_whole_replay() {
+        match parse_command(to_args("export game.replay --output board.html")).unwrap() {
+            Command::Export { frame, every, output, .. } => {
+                assert_eq!(frame, None);
+                assert_eq!(every, 12);
+                assert_eq!(output.to_str(), Some("board.html"));
+            },
+            command => panic!("Expected an export, found {:?}", command)
+        }
+        assert!(parse_command(to_args("export game.replay --frame soon")).is_err());
+    }
+
+
+    #[test]
+    fn theme_is_a_built_in_name_or_a_file() {
+        assert_eq!(parse(to_args("--theme ascii")).unwrap().display_options.theme, Theme::from_name("ascii").unwrap());
+        assert!(parse(to_args("--theme themes/high-contrast.txt")).is_ok());
+        assert!(parse(to_args("--theme themes/missing.txt")).is_err());
+    }
+
+
+    #[test]
+    fn colorblind_palette_and_piece_letters_are_display_options() {
+        let result = parse(to_args("--palette tritanopia --piece-letters")).unwrap();
+
+        assert_eq!(result.display_options.palette, Some(Palette::Tritanopia));
+        assert!(result.display_options.piece_letters);
+        assert!(parse(to_args("--palette sepia")).is_err());
+    }
+}
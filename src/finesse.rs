@@ -0,0 +1,203 @@
+use std::collections::{HashSet, VecDeque};
+
+use nalgebra::Vector2;
+
+use crate::{game_event::GameEvent, gameboard::{BoardDimensions, MovementDirection, Action}, piece::{Piece, RotationType}};
+
+// Placements needing more inputs than this are not judged
+const MAX_SEARCHED_INPUTS: u32 = 16;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct FinesseFault {
+    pub used: u32,
+    pub needed: u32
+}
+
+// Compares the inputs of every piece to the fewest reaching the same placement
+pub struct FinesseTracker {
+    dimensions: BoardDimensions,
+    inputs: u32,
+    soft_dropped: bool,
+    faults: u32,
+    last_fault: Option<FinesseFault>
+}
+
+impl FinesseTracker {
+
+    pub fn new(dimensions: BoardDimensions) -> Self {
+        FinesseTracker {
+            dimensions,
+            inputs: 0,
+            soft_dropped: false,
+            faults: 0,
+            last_fault: None
+        }
+    }
+
+
+    pub fn get_faults(&self) -> u32 {
+        self.faults
+    }
+
+
+    pub fn get_last_fault(&self) -> Option<FinesseFault> {
+        self.last_fault
+    }
+
+
+    pub fn record(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::PieceSpawned(_) | GameEvent::Held => {
+                self.inputs = 0;
+                self.soft_dropped = false;
+            },
+            // Presses count even when they do not move the piece
+            GameEvent::Input(Action::Move(MovementDirection::Left | MovementDirection::Right) | Action::Rotate) => self.inputs += 1,
+            GameEvent::Moved(MovementDirection::Bottom) => self.soft_dropped = true,
+            GameEvent::Locked(piece) => {
+                // Soft drops lead to tucks and spins, out of reach from above
+                if self.soft_dropped {
+                    self.last_fault = None;
+                    return;
+                }
+                let mut spawned = piece.clone();
+                spawned.reset_rotation();
+                spawned.move_at(self.dimensions.get_play_point());
+                self.last_fault = get_minimal_inputs(&spawned, piece, self.dimensions.width)
+                    .filter(|needed| self.inputs > *needed)
+                    .map(|needed| FinesseFault { used: self.inputs, needed });
+                if let Some(fault) = self.last_fault {
+                    self.faults += fault.used - fault.needed;
+                }
+            },
+            _ => ()
+        }
+    }
+}
+
+
+// Column and shape of a piece, the height it is dropped from does not matter
+fn get_placement(piece: &Piece) -> (isize, Vec<Vec<bool>>) {
+    let min_x = piece.get_squares().iter().map(|square| square.get_position().x).min().unwrap_or(0);
+    (min_x, piece.to_array())
+}
+
+
+fn is_inside(piece: &Piece, width: usize) -> bool {
+    piece.get_squares().iter().all(|square| (0..width as isize).contains(&square.get_position().x))
+}
+
+
+// Rotation on an empty board, kicks only being needed against the walls
+fn rotated(piece: &Piece, width: usize) -> Option<Piece> {
+    if piece.get_rotation_type() == RotationType::None {
+        return None;
+    }
+
+    let offsets = std::iter::once(Vector2::new(0, 0)).chain(piece.get_kicks().iter().copied());
+    for offset in offsets {
+        let mut candidate = piece.clone();
+        candidate.translate(offset);
+        candidate.rotate();
+        if is_inside(&candidate, width) {
+            return Some(candidate);
+        }
+    }
+    None
+}
+
+
+// Fewest left, right and rotate inputs bringing the piece from its spawn to the target
+// column and orientation, searched breadth first on an empty board
+pub fn get_minimal_inputs(spawned: &Piece, target: &Piece, width: usize) -> Option<u32> {
+    let goal = get_placement(target);
+    let key = |piece: &Piece| piece.get_squares().iter().map(|square| square.get_position()).collect::<Vec<_>>();
+
+    let mut visited = HashSet::from([key(spawned)]);
+    let mut queue = VecDeque::from([(spawned.clone(), 0)]);
+    while let Some((piece, inputs)) = queue.pop_front() {
+        if get_placement(&piece) == goal {
+            return Some(inputs);
+        }
+        if inputs >= MAX_SEARCHED_INPUTS {
+            continue;
+        }
+
+        let mut next = vec![];
+        for shift in [-1, 1] {
+            let mut moved = piece.clone();
+            moved.translate(Vector2::new(shift, 0));
+            next.push(Some(moved).filter(|moved| is_inside(moved, width)));
+        }
+        next.push(rotated(&piece, width));
+
+        for candidate in next.into_iter().flatten() {
+            if visited.insert(key(&candidate)) {
+                queue.push_back((candidate, inputs + 1));
+            }
+        }
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod test {
+    use nalgebra::Vector2;
+
+    use crate::{game_event::GameEvent, gameboard::{BoardDimensions, MovementDirection, Action, BOARD_WIDTH}, piece::Piece, piece_factory::PieceType};
+
+    use super::{get_minimal_inputs, FinesseTracker, FinesseFault};
+
+    fn spawned(piece_type: PieceType) -> Piece {
+        let mut piece = Piece::from(piece_type);
+        piece.move_at(BoardDimensions::default().get_play_point());
+        piece
+    }
+
+    #[test]
+    fn wall_placements_need_the_moves_to_reach_the_wall() {
+        let piece = spawned(PieceType::T);
+        let mut target = piece.clone();
+        target.translate(Vector2::new(-3, 0));
+
+        assert_eq!(get_minimal_inputs(&piece, &target, BOARD_WIDTH), Some(3));
+    }
+
+
+    #[test]
+    fn symmetric_orientations_are_reached_with_fewer_rotations() {
+        let piece = spawned(PieceType::S);
+        let mut target = piece.clone();
+        for _ in 0..3 {
+            target.rotate();
+        }
+
+        // Three rotations of an S give the shape of a single one, a column aside
+        assert_eq!(get_minimal_inputs(&piece, &target, BOARD_WIDTH), Some(2));
+    }
+
+
+    #[test]
+    fn extra_inputs_are_reported_as_faults() {
+        let mut sut = FinesseTracker::new(BoardDimensions::default());
+        let mut piece = spawned(PieceType::L);
+        piece.translate(Vector2::new(1, 0));
+
+        sut.record(&GameEvent::PieceSpawned(PieceType::L));
+        for direction in [MovementDirection::Right, MovementDirection::Right, MovementDirection::Left] {
+            sut.record(&GameEvent::Input(Action::Move(direction)));
+        }
+        sut.record(&GameEvent::Locked(piece.clone()));
+
+        assert_eq!(sut.get_last_fault(), Some(FinesseFault { used: 3, needed: 1 }));
+        assert_eq!(sut.get_faults(), 2);
+
+        sut.record(&GameEvent::PieceSpawned(PieceType::L));
+        sut.record(&GameEvent::Input(Action::Move(MovementDirection::Right)));
+        sut.record(&GameEvent::Locked(piece));
+
+        assert!(sut.get_last_fault().is_none());
+        assert_eq!(sut.get_faults(), 2);
+    }
+}
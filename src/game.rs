@@ -0,0 +1,237 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{gameboard::{GameBoard, MovementDirection, Action}, cli::Options, game_mode::{GameMode, INSTANT_GRAVITY}, piece_provider::PieceProvider, game_stats::GameStats, replay::Replay, game_event::GameEvent};
+
+pub const STEPS_PER_SECOND: u32 = 120;
+pub const STEP_TIME: f32 = 1.0 / STEPS_PER_SECOND as f32;
+
+// A game advanced by fixed steps, so the same seed and inputs always give the same game
+pub struct Game {
+    board: GameBoard,
+    mode: Box<dyn GameMode>,
+    stats: GameStats,
+    fall_progress: f32,
+    lock_timer: f32,
+    frame: u32,
+    replay: Replay,
+    // Played events not drained yet by the renderer
+    events: Vec<GameEvent>
+}
+
+impl Game {
+
+    pub fn new(options: &Options, seed: u64) -> Self {
+        let mode = options.mode.create(&options.mode_options);
+        let rng = ChaCha8Rng::seed_from_u64(seed);
+        let level = 1;
+        let mut board = GameBoard::new(rng, level);
+        let mut rules = mode.get_rules();
+        rules.visibility = options.mode_options.visibility;
+        board.set_rules(rules);
+        board.set_dimensions(mode.get_dimensions());
+        board.set_scoring(options.mode_options.scoring.unwrap_or(mode.get_scoring()).create());
+        if let Some(piece_set) = options.mode_options.piece_set.clone().or_else(|| mode.get_piece_set()) {
+            let rng = ChaCha8Rng::seed_from_u64(seed);
+            board.set_piece_provider(PieceProvider::with_piece_set(piece_set, rng));
+        }
+        mode.prepare(&mut board);
+        board.drain_events();
+
+        Game {
+            stats: GameStats::new(board.get_dimensions()),
+            board,
+            mode,
+            fall_progress: 0.0,
+            lock_timer: 0.0,
+            frame: 0,
+            replay: Replay::new(seed, options.mode, options.mode_options.clone()),
+            events: vec![]
+        }
+    }
+
+
+    pub fn get_board(&self) -> &GameBoard {
+        &self.board
+    }
+
+
+    pub fn get_stats(&self) -> &GameStats {
+        &self.stats
+    }
+
+
+    pub fn get_frame(&self) -> u32 {
+        self.frame
+    }
+
+
+    pub fn get_replay(&self) -> &Replay {
+        &self.replay
+    }
+
+
+    pub fn get_result(&self) -> Option<String> {
+        self.mode.get_result()
+    }
+
+
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+
+    pub fn is_over(&self) -> bool {
+        !self.board.keep_playing()
+    }
+
+
+    // Inputs are recorded with the frame they are applied before
+    pub fn apply(&mut self, action: Action) {
+        self.replay.record(self.frame, action);
+        self.board.report_input(action);
+        match action {
+            Action::Move(MovementDirection::Top) => {
+                // A grounded piece cannot move but a hard drop still locks it
+                let _ = self.board.try_move(MovementDirection::Top);
+                self.board.lock_current_piece();
+                self.lock_timer = 0.0;
+                self.fall_progress = 0.0;
+                self.update_mode(0.0);
+            },
+            Action::Move(movement) => {
+                if self.board.try_move(movement).is_ok() {
+                    self.lock_timer = 0.0;
+                }
+            },
+            Action::Rotate => {
+                if self.board.try_rotate().is_ok() {
+                    self.lock_timer = 0.0;
+                }
+            },
+            Action::Hold => {
+                if self.board.try_swap().is_ok() {
+                    self.lock_timer = 0.0;
+                }
+            }
+        }
+    }
+
+
+    // Returns whether anything to draw changed
+    pub fn step(&mut self, time_delta: f32) -> bool {
+        let mut update = false;
+        self.frame += 1;
+        self.board.advance_time(time_delta);
+        self.stats.advance_time(time_delta);
+
+        if self.board.is_grounded() {
+            self.lock_timer += time_delta;
+            if self.mode.get_lock_delay().is_some_and(|delay| self.lock_timer >= delay) {
+                self.board.lock_current_piece();
+                self.lock_timer = 0.0;
+                self.fall_progress = 0.0;
+            }
+        } else {
+            self.lock_timer = 0.0;
+        }
+
+        if let Some(gravity) = self.mode.get_gravity() {
+            self.fall_progress += gravity * time_delta;
+            let rows = match gravity >= INSTANT_GRAVITY {
+                true => self.board.get_dimensions().height,
+                false => self.fall_progress as usize
+            };
+            if rows > 0 {
+                self.fall_progress -= rows as f32;
+                if self.board.try_fall_by(rows).is_ok() {
+                    update = true;
+                }
+                self.fall_progress = f32::max(self.fall_progress, 0.0);
+            }
+        }
+
+        update |= self.update_mode(time_delta);
+        update
+    }
+
+
+    // Also run right after a hard drop, the lock of the last piece of a queue ending the game before any step
+    fn update_mode(&mut self, time_delta: f32) -> bool {
+        self.mode.update(&mut self.board, time_delta);
+        if self.mode.is_finished() {
+            self.board.finish();
+        }
+
+        // Anything the board reports changes what is drawn
+        let events = self.board.drain_events();
+        for event in events.iter() {
+            self.stats.record(event);
+        }
+        let changed = !events.is_empty();
+        self.events.extend(events);
+        changed
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use crate::{cli::Options, game_mode::{ModeKind, ModeOptions}, gameboard::{Action, MovementDirection, BlockVisibility}, piece_factory::PieceSet, puzzle_mode::Puzzle};
+
+    use super::{Game, STEP_TIME};
+
+    #[test]
+    fn presses_against_a_wall_are_counted_as_inputs() {
+        let mut sut = Game::new(&Options::default(), 1);
+
+        for _ in 0..10 {
+            sut.apply(Action::Move(MovementDirection::Left));
+        }
+        sut.apply(Action::Move(MovementDirection::Top));
+        sut.step(STEP_TIME);
+
+        assert_eq!(sut.get_stats().get_keys_per_piece(), 11.0);
+    }
+
+
+    #[test]
+    fn hidden_squares_are_revealed_when_the_mode_is_finished() {
+        // Each drop of this piece clears its full middle row and fills the half row left by the previous one
+        let cells = "-4 -1, -3 -1, -2 -1, -1 -1, 0 -1, -4 0, -3 0, -2 0, -1 0, 0 0, 1 0, 2 0, 3 0, 4 0, 5 0, 1 1, 2 1, 3 1, 4 1, 5 1";
+        let piece_set = PieceSet::parse(&format!("piece: W\nrotation: none\ncells: {}", cells)).unwrap();
+        let mode_options = ModeOptions { visibility: BlockVisibility::Invisible, piece_set: Some(piece_set), ..ModeOptions::default() };
+        let options = Options { mode: ModeKind::Master, mode_options, ..Options::default() };
+        let mut sut = Game::new(&options, 1);
+
+        while !sut.is_over() {
+            sut.apply(Action::Move(MovementDirection::Top));
+            sut.step(STEP_TIME);
+        }
+
+        let board = sut.get_board();
+        let squares = board.get_square_board().iter().flatten().flatten().collect::<Vec<_>>();
+        assert_eq!(board.get_level(), 999);
+        assert!(!squares.is_empty());
+        assert!(squares.iter().all(|square| board.is_square_shown(square)));
+    }
+
+
+    #[test]
+    fn puzzles_are_solved_by_the_hard_drop_of_their_last_piece() {
+        let puzzle = Puzzle::parse(include_str!("../puzzles/perfect_clear.txt")).unwrap();
+        let mode_options = ModeOptions { puzzle: Some(puzzle), ..ModeOptions::default() };
+        let options = Options { mode: ModeKind::Puzzle, mode_options, ..Options::default() };
+        let mut sut = Game::new(&options, 1);
+
+        for _ in 0..2 {
+            sut.apply(Action::Move(MovementDirection::Right));
+            sut.apply(Action::Move(MovementDirection::Top));
+        }
+
+        assert!(sut.is_over());
+        assert_eq!(sut.get_result(), Some(String::from("Puzzle solved !")));
+        assert_eq!(sut.get_stats().get_keys_per_piece(), 2.0);
+    }
+}
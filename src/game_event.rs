@@ -0,0 +1,31 @@
+use crate::{gameboard::{MovementDirection, LineClear, Action}, piece::Piece, piece_factory::PieceType};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameOverReason {
+    // The new piece overlaps the stack
+    TopOut,
+    // A fixed sequence of pieces has been played entirely
+    EmptyQueue
+}
+
+// Everything the board reports, drained by the consumers once per frame
+#[derive(Clone, PartialEq, Debug)]
+pub enum GameEvent {
+    PieceSpawned(PieceType),
+    // Key pressed by the player, whether the action succeeded or not
+    Input(Action),
+    // Player moves only, gravity falls are not reported
+    Moved(MovementDirection),
+    // Index of the kick used, None for a regular rotation
+    Rotated(Option<usize>),
+    Held,
+    // The piece as it was placed
+    Locked(Piece),
+    // Rows are given from the top, a T-spin without line has no row
+    LinesCleared {
+        rows: Vec<usize>,
+        kind: LineClear
+    },
+    LevelUp(u32),
+    GameOver(GameOverReason)
+}
@@ -1,148 +1,452 @@
+use std::{thread, path::Path, time::{Instant, Duration}};
 
-use std::{io::stdin, thread, time::Instant, sync::mpsc::{self}};
+use colored::Colorize;
+use rand::Rng;
+use strum::IntoEnumIterator;
 
-use console::Term;
-use rand::{SeedableRng, Rng};
-use rand_chacha::ChaCha8Rng;
+use crate::{gameboard::BlockVisibility, cli::Options, game::{Game, STEP_TIME}, game_mode::ModeKind, scoring::ScoringKind, theme::{Theme, Palette}, input::{Input, InputEvent}, menu::{self, Menu, MenuAction}, high_scores::{HighScores, HighScore}, terminal::TerminalGuard, key_bindings::{KeyBindings, Control, Preset, GameInput}, layout::Panel, graphics::Visualizer};
 
-use crate::{gameboard::{GameBoard, MovementDirection, Action}, graphics::{AsciiVisualizer, Visualizer}};
+// Fading squares need a redraw even when nothing moves
+pub const FADE_REFRESH_TIME: f32 = 0.1;
+// Seconds locked squares stay visible when fading is picked in the settings
+const DEFAULT_FADE_TIME: f32 = 5.0;
+
+enum Screen {
+    Title,
+    ModeSelect,
+    Settings,
+    Controls,
+    HighScores(ModeKind),
+    Play,
+    GameOver(Box<GameOver>),
+    Quit
+}
+
+// A finished game, still drawn by the visualizer it was played on
+struct GameOver {
+    game: Game,
+    visualizer: Box<dyn Visualizer>,
+    // Rank in the high scores
+    rank: Option<usize>,
+    // Files that could not be written once the game ended
+    errors: Vec<String>
+}
+
+// How a game was left
+enum Ending {
+    Over,
+    Abandoned,
+    Quit
+}
 
-pub const FPS: u32 = 120;
-pub const SLEEP_TIME: f32 = 1.0/FPS as f32;
-pub const SPEED_FACTOR: f32 = 0.8;
-pub const MIN_LOCK_DELAY: f32 = 0.4;
 pub struct GameManager;
 
 impl GameManager {
 
-    pub fn start() {
+    pub fn start(options: &Options) {
+        let mut options = options.clone();
+        // Read before the terminal is taken over, a file that cannot be read is never written over
+        let mut errors = vec![];
+        let mut bindings_path = KeyBindings::get_path();
+        let bindings = match bindings_path.as_deref().map(KeyBindings::load) {
+            Some(Ok(bindings)) => bindings,
+            Some(Err(error)) => {
+                errors.push(format!("{}, default keys are used and changes are not saved", error));
+                bindings_path = None;
+                KeyBindings::default()
+            },
+            None => KeyBindings::default()
+        };
+        let mut high_scores_path = HighScores::get_path();
+        let mut high_scores = match high_scores_path.as_deref().map(HighScores::load) {
+            Some(Ok(high_scores)) => high_scores,
+            Some(Err(error)) => {
+                errors.push(format!("{}, new high scores are not saved", error));
+                high_scores_path = None;
+                HighScores::default()
+            },
+            None => HighScores::default()
+        };
 
-        let mut fall_time = 1.0 / 4.0;
-        let mut lock_delay = 1.5 * fall_time;
-        let seed = rand::thread_rng().gen::<u64>();
-        let stdout = Term::buffered_stdout();
-        let rng = ChaCha8Rng::seed_from_u64(seed);
-        let mut level = 1;
-        let mut hold_lines_cleared = 0;
-        let mut board = GameBoard::new(rng, level);
-
-        let (to_main, from_thread) = mpsc::channel::<Action>();
-        let keyboard_listener = thread::spawn(move || {
-            loop {
-                if let Ok(character) = stdout.read_char() {
-                    let _ = match character {
-                        ' ' => to_main.send(Action::Move(MovementDirection::Top)),
-                        'q' => to_main.send(Action::Move(MovementDirection::Left)),
-                        's' => to_main.send(Action::Move(MovementDirection::Bottom)),
-                        'd' => to_main.send(Action::Move(MovementDirection::Right)),
-                        'z' => to_main.send(Action::Rotate),
-                        '\n' => to_main.send(Action::Hold),
-                        _ => Ok(())
+        // Dropped last, once the input thread is stopped
+        let _terminal = TerminalGuard::new();
+        let mut input = Input::spawn(bindings);
+        if !errors.is_empty() && !GameManager::show_errors(&errors, &input) {
+            return;
+        }
+
+        let mut screen = Screen::Title;
+        loop {
+            screen = match screen {
+                Screen::Title => GameManager::show_title(&input),
+                Screen::ModeSelect => GameManager::select_mode(&mut options, &input),
+                Screen::Settings => GameManager::edit_settings(&mut options, &input),
+                Screen::Controls => GameManager::edit_controls(&mut input, bindings_path.as_deref()),
+                Screen::HighScores(mode) => GameManager::show_high_scores(&high_scores, mode, &input),
+                Screen::Play => {
+                    let (game, ending, visualizer) = GameManager::play(&options, &input);
+                    let mut errors = vec![];
+                    if let Some(path) = &options.record {
+                        let mut replay = game.get_replay().clone();
+                        replay.set_frames(game.get_frame());
+                        errors.extend(replay.save(path).err().map(|error| error.to_string()));
+                    }
+                    match ending {
+                        Ending::Over => {
+                            let board = game.get_board();
+                            let high_score = HighScore { score: board.get_score(), lines: board.get_lines_cleared(), level: board.get_level() };
+                            let rank = high_scores.add(options.mode, high_score);
+                            if let (Some(_), Some(path)) = (rank, &high_scores_path) {
+                                errors.extend(high_scores.save(path).err().map(|error| error.to_string()));
+                            }
+                            Screen::GameOver(Box::new(GameOver { game, visualizer, rank, errors }))
+                        },
+                        ending => {
+                            let read = errors.is_empty() || GameManager::show_errors(&errors, &input);
+                            match ending {
+                                Ending::Abandoned if read => Screen::Title,
+                                _ => Screen::Quit
+                            }
+                        }
+                    }
+                },
+                Screen::GameOver(mut game_over) => GameManager::show_game_over(&mut game_over, &input),
+                Screen::Quit => break
+            };
+        }
+    }
+
+
+    // Shows the menu until something is picked, None when the player quits or no key can be read anymore
+    fn choose(menu: &mut Menu, input: &Input) -> Option<MenuAction> {
+        GameManager::choose_with(menu, input, menu::show)
+    }
+
+
+    // Same as choose, the menu and its hint being drawn by show
+    fn choose_with<F: FnMut(&Panel)>(menu: &mut Menu, input: &Input, mut show: F) -> Option<MenuAction> {
+        let bindings = input.get_bindings();
+        let hint = format!("{}/{} move, {}/{} change, enter select, {} quit",
+            bindings.get_key_name(Control::Rotate), bindings.get_key_name(Control::SoftDrop),
+            bindings.get_key_name(Control::Left), bindings.get_key_name(Control::Right), bindings.get_key_name(Control::Quit));
+        let hint = Panel::new(vec![hint.dimmed().to_string()]);
+        loop {
+            show(&menu.render().above(hint.clone()));
+            let key = input.read().filter(|key| !bindings.is_quit(*key))?;
+            match bindings.get_menu_input(key).map(|menu_input| menu.handle(menu_input)) {
+                Some(MenuAction::None) | None => continue,
+                Some(action) => return Some(action)
+            }
+        }
+    }
+
+
+    fn show_title(input: &Input) -> Screen {
+        let mut menu = Menu::new("T E T R U S T", &["Play", "Settings", "High scores", "Quit"]);
+        match GameManager::choose(&mut menu, input) {
+            Some(MenuAction::Select(0)) => Screen::ModeSelect,
+            Some(MenuAction::Select(1)) => Screen::Settings,
+            Some(MenuAction::Select(2)) => Screen::HighScores(ModeKind::Marathon),
+            _ => Screen::Quit
+        }
+    }
+
+
+    // The puzzle mode is only offered when a puzzle was given on the command line
+    fn select_mode(options: &mut Options, input: &Input) -> Screen {
+        let modes: Vec<ModeKind> = ModeKind::iter()
+            .filter(|mode| *mode != ModeKind::Puzzle || options.mode_options.puzzle.is_some())
+            .collect();
+        let mut labels: Vec<&str> = modes.iter().map(ModeKind::get_name).collect();
+        labels.push("Back");
+
+        let mut menu = Menu::new("Mode", &labels);
+        menu.set_selected(modes.iter().position(|mode| *mode == options.mode).unwrap_or(0));
+        match GameManager::choose(&mut menu, input) {
+            Some(MenuAction::Select(index)) if index < modes.len() => {
+                options.mode = modes[index];
+                Screen::Play
+            },
+            Some(_) => Screen::Title,
+            None => Screen::Quit
+        }
+    }
+
+
+    fn edit_settings(options: &mut Options, input: &Input) -> Screen {
+        let labels = ["Infinite hold", "Blocks", "Scoring", "Theme", "Palette", "Piece letters", "Controls", "Back"];
+        let mut selected = 0;
+        loop {
+            let mut menu = Menu::new("Settings", &labels);
+            menu.set_selected(selected);
+            menu.set_value(0, on_off(options.mode_options.infinite_hold));
+            menu.set_value(1, get_visibility_name(options.mode_options.visibility));
+            menu.set_value(2, options.mode_options.scoring.map_or("mode", |scoring| scoring.get_name()).to_string());
+            menu.set_value(3, options.display_options.theme.get_name().unwrap_or("custom").to_string());
+            menu.set_value(4, options.display_options.palette.map_or("theme", |palette| palette.get_name()).to_string());
+            menu.set_value(5, on_off(options.display_options.piece_letters));
+
+            let (index, step) = match GameManager::choose(&mut menu, input) {
+                Some(MenuAction::Select(6)) => return Screen::Controls,
+                Some(MenuAction::Select(7)) => return Screen::Title,
+                Some(MenuAction::Select(index)) => (index, 1),
+                Some(MenuAction::Change(index, step)) => (index, step),
+                _ => return Screen::Quit
+            };
+            selected = index;
+
+            let mode_options = &mut options.mode_options;
+            let display_options = &mut options.display_options;
+            match index {
+                0 => mode_options.infinite_hold = !mode_options.infinite_hold,
+                1 => {
+                    let fade_time = match mode_options.visibility {
+                        BlockVisibility::Fading(seconds) => seconds,
+                        _ => DEFAULT_FADE_TIME
                     };
+                    let visibilities = [BlockVisibility::Visible, BlockVisibility::Fading(fade_time), BlockVisibility::Invisible];
+                    mode_options.visibility = cycle(&visibilities, &mode_options.visibility, step);
+                },
+                2 => {
+                    let scorings: Vec<Option<ScoringKind>> = std::iter::once(None).chain(ScoringKind::iter().map(Some)).collect();
+                    mode_options.scoring = cycle(&scorings, &mode_options.scoring, step);
+                },
+                3 => {
+                    let themes: Vec<Theme> = Theme::get_built_in_names().into_iter().filter_map(Theme::from_name).collect();
+                    display_options.theme = cycle(&themes, &display_options.theme, step);
+                },
+                4 => {
+                    let palettes: Vec<Option<Palette>> = std::iter::once(None).chain(Palette::iter().map(Some)).collect();
+                    display_options.palette = cycle(&palettes, &display_options.palette, step);
+                },
+                5 => display_options.piece_letters = !display_options.piece_letters,
+                _ => ()
+            }
+        }
+    }
+
+
+    // Changes are saved as soon as they are made, when there is a file to save them to
+    fn edit_controls(input: &mut Input, path: Option<&Path>) -> Screen {
+        let controls: Vec<Control> = Control::iter().collect();
+        let mut labels = vec!["Preset"];
+        labels.extend(controls.iter().map(Control::get_label));
+        labels.push("Back");
+        let mut selected = 0;
+        loop {
+            let mut bindings = input.get_bindings().clone();
+            let mut menu = Menu::new("Controls", &labels);
+            menu.set_selected(selected);
+            menu.set_value(0, bindings.get_preset().map_or("custom", |preset| preset.get_name()).to_string());
+            for (index, control) in controls.iter().enumerate() {
+                let names: Vec<String> = bindings.get_keys(*control).iter().map(InputEvent::get_name).collect();
+                menu.set_value(index + 1, names.join(" "));
+            }
+
+            let presets: Vec<Option<Preset>> = Preset::iter().map(Some).collect();
+            match GameManager::choose(&mut menu, input) {
+                Some(MenuAction::Select(0)) => {
+                    bindings = cycle(&presets, &bindings.get_preset(), 1).unwrap().get_bindings();
+                    selected = 0;
+                },
+                Some(MenuAction::Change(0, step)) => {
+                    bindings = cycle(&presets, &bindings.get_preset(), step).unwrap().get_bindings();
+                    selected = 0;
+                },
+                Some(MenuAction::Select(index)) if index <= controls.len() => {
+                    let control = controls[index - 1];
+                    menu::show(&Panel::new(vec![
+                        format!("Press a key to {}", control.get_label().to_lowercase()).bold().to_string(),
+                        String::new(),
+                        String::from("ctrl+c to cancel").dimmed().to_string()
+                    ]));
+                    match input.read() {
+                        Some(InputEvent::Ctrl('c')) => (),
+                        Some(key) => bindings.bind(control, key),
+                        None => return Screen::Quit
+                    }
+                    selected = index;
+                },
+                Some(MenuAction::Select(_)) => return Screen::Settings,
+                Some(MenuAction::Change(index, _)) => selected = index,
+                _ => return Screen::Quit
+            }
+
+            if bindings != *input.get_bindings() {
+                let saved = path.map(|path| bindings.save(path));
+                input.set_bindings(bindings);
+                if let Some(Err(error)) = saved {
+                    if !GameManager::show_errors(&[error.to_string()], input) {
+                        return Screen::Quit;
+                    }
                 }
             }
-        });
-        
+        }
+    }
+
+
+    fn show_high_scores(high_scores: &HighScores, mode: ModeKind, input: &Input) -> Screen {
+        let entries = high_scores.get_entries(mode);
+        let mut text = vec![format!("{:<4}{:>10}{:>8}{:>8}", "#", "Score", "Lines", "Level")];
+        for (rank, entry) in entries.iter().enumerate() {
+            text.push(format!("{:<4}{:>10}{:>8}{:>8}", rank + 1, entry.score, entry.lines, entry.level));
+        }
+        if entries.is_empty() {
+            text.push(String::from("No game played yet"));
+        }
+
+        let mut menu = Menu::new("High scores", &["Mode", "Back"]).with_text(text);
+        menu.set_value(0, mode.get_name().to_string());
+        let modes: Vec<ModeKind> = ModeKind::iter().collect();
+        match GameManager::choose(&mut menu, input) {
+            Some(MenuAction::Change(_, step)) => Screen::HighScores(cycle(&modes, &mode, step)),
+            Some(MenuAction::Select(0)) => Screen::HighScores(cycle(&modes, &mode, 1)),
+            Some(_) => Screen::Title,
+            None => Screen::Quit
+        }
+    }
+
+
+    // The menu is drawn beside the final board
+    fn show_game_over(game_over: &mut GameOver, input: &Input) -> Screen {
+        let GameOver { game, visualizer, rank, errors } = game_over;
+        let board = game.get_board();
+        let mut text = vec![];
+        if let Some(result) = game.get_result() {
+            text.push(result);
+        }
+        text.push(format!("Score {}, {} lines, level {}", board.get_score(), board.get_lines_cleared(), board.get_level()));
+        if let Some(rank) = rank {
+            text.push(format!("New high score, rank {}", rank));
+        }
+        text.extend(errors.iter().map(|error| error.red().to_string()));
+        text.push(String::new());
+        for (label, value) in game.get_stats().get_summary() {
+            text.push(format!("{:<11}{}", label, value));
+        }
+
+        let mut menu = Menu::new("Game over", &["Retry", "Menu", "Quit"]).with_text(text);
+        match GameManager::choose_with(&mut menu, input, |panel| visualizer.display_beside(board, panel)) {
+            Some(MenuAction::Select(0)) => Screen::Play,
+            Some(MenuAction::Select(1)) => Screen::Title,
+            _ => Screen::Quit
+        }
+    }
+
+
+    // Waits for a key once the errors are read, false when no key can be read anymore
+    fn show_errors(errors: &[String], input: &Input) -> bool {
+        let mut lines: Vec<String> = errors.iter().map(|error| error.red().to_string()).collect();
+        lines.push(String::new());
+        lines.push(String::from("Press a key to continue").dimmed().to_string());
+        menu::show(&Panel::new(lines));
+        input.read().is_some()
+    }
+
+
+    // Shown instead of the board, None to resume the game
+    fn pause(input: &Input) -> Option<Ending> {
+        let mut menu = Menu::new("Paused", &["Resume", "Menu", "Quit"]);
+        match GameManager::choose(&mut menu, input) {
+            Some(MenuAction::Select(0)) => None,
+            Some(MenuAction::Select(1)) => Some(Ending::Abandoned),
+            _ => Some(Ending::Quit)
+        }
+    }
+
+
+    fn play(options: &Options, input: &Input) -> (Game, Ending, Box<dyn Visualizer>) {
+        let seed = rand::thread_rng().gen::<u64>();
+        let mut game = Game::new(options, seed);
+
+        let mut visualizer = options.renderer.create(&options.display_options);
+        visualizer.display(game.get_board(), game.get_stats());
 
-        AsciiVisualizer::display(&board);
-        
         let mut update = true;
-        let mut last_fall = 0.0;
-        let mut lock_timer = 0.0;
+        let mut fade_timer = 0.0;
+        // Real time not yet played, the game only moves by whole steps
+        let mut pending_time = 0.0;
         let mut start = Instant::now();
-        while board.keep_playing() {
-
-            let _ = match from_thread.try_recv() {
-                Ok(Action::Move(movement)) => {
-                    let _ = match board.try_move(movement) {
-                        Ok(_) => {
-                            match movement {
-                                MovementDirection::Top => {
-                                    lock_timer = lock_delay;
-                                    last_fall = fall_time;
-                                }
-                                _ => lock_timer = 0.0
-                            }
-                        }
-                        Err(_) => ()
-                    };
+        let mut ending = Ending::Over;
+        while !game.is_over() {
+
+            match input.try_read().and_then(|key| input.get_bindings().get_game_input(key)) {
+                Some(GameInput::Play(action)) => {
+                    game.apply(action);
                     update = true;
                 },
-                Ok(Action::Rotate) => {
-                    let _ = match board.try_rotate(){
-                        Ok(_) => lock_timer = 0.0,
-                        Err(_) => ()
-                    };
+                Some(GameInput::Pause) => {
+                    visualizer.hide();
+                    if let Some(left) = GameManager::pause(input) {
+                        ending = left;
+                        break;
+                    }
+                    // Time spent paused is not played
+                    start = Instant::now();
                     update = true;
                 },
-                Ok(Action::Hold) => {
-                    let _ = match board.try_swap(){
-                        Ok(_) => lock_timer = 0.0,
-                        Err(_) => ()
-                    };
-                    update = true;
+                Some(GameInput::Quit) => {
+                    ending = Ending::Quit;
+                    break;
                 },
-                Err(_) => ()
-            };
-            
+                None => ()
+            }
+
             let time_delta = start.elapsed().as_secs_f32();
-            last_fall += time_delta;
-            lock_timer += time_delta;
             start = Instant::now();
-            if last_fall >= fall_time {
-                match board.try_fall() {
-                    Ok(_) => {
-                        lock_timer = 0.0;
-                        let cleared = board.get_lines_cleared();
-                        if cleared % 2 == 0 &&
-                        hold_lines_cleared != cleared {
-                            level += 1;
-                            board.set_level(level);
-                            fall_time *= SPEED_FACTOR;
-                            lock_delay *= SPEED_FACTOR + (1 as f32 - SPEED_FACTOR) / 2.0;
-                            lock_delay = f32::min(lock_delay, MIN_LOCK_DELAY);
-                            hold_lines_cleared = cleared;   
-                        }
-                    },
-                    Err(_) => {
-                        if lock_timer >= lock_delay {
-                            board.lock_current_piece();
-                        }
-                    }
+            pending_time += time_delta;
+            while pending_time >= STEP_TIME && !game.is_over() {
+                pending_time -= STEP_TIME;
+                update |= game.step(STEP_TIME);
+            }
+            for event in game.drain_events() {
+                visualizer.record(&event);
+            }
+            visualizer.advance_time(time_delta);
+
+            if let BlockVisibility::Fading(_) = options.mode_options.visibility {
+                fade_timer += time_delta;
+                if fade_timer >= FADE_REFRESH_TIME {
+                    fade_timer = 0.0;
+                    update = true;
                 }
-                last_fall = 0.0;
-                update = true;
             }
-            
-            if update {
-                AsciiVisualizer::display(&board);
+
+            if update || visualizer.needs_redraw() {
+                visualizer.display(game.get_board(), game.get_stats());
                 update = false;
             }
-        }
 
-        keyboard_listener.join().unwrap();
+            thread::sleep(Duration::from_secs_f32(STEP_TIME));
+        }
 
-        GameManager::end();
+        // Hidden squares are revealed once the game is over
+        if let Ending::Over = ending {
+            visualizer.display(game.get_board(), game.get_stats());
+        }
+        (game, ending, visualizer)
     }
+}
 
 
-    pub fn end() {
-        
-        let mut s=String::new();
-        println!("Please enter some text: ");
-        stdin().read_line(&mut s).expect("Did not enter a correct string");
-        if let Some('\n')=s.chars().next_back() {
-            s.pop();
-        }
-        if let Some('\r')=s.chars().next_back() {
-            s.pop();
-        }
-        
-        if let Some('r') = s.chars().next_back() {
-            GameManager::start();
-        }
-    }
+fn on_off(value: bool) -> String {
+    String::from(if value { "on" } else { "off" })
+}
 
+
+fn get_visibility_name(visibility: BlockVisibility) -> String {
+    match visibility {
+        BlockVisibility::Visible => String::from("visible"),
+        BlockVisibility::Fading(seconds) => format!("fading {}s", seconds),
+        BlockVisibility::Invisible => String::from("invisible")
+    }
 }
 
 
+// Value before or after the current one, values not in the list going back to the first
+fn cycle<T: Clone + PartialEq>(values: &[T], current: &T, step: isize) -> T {
+    let index = values.iter()
+        .position(|value| value == current)
+        .map_or(0, |index| (index as isize + step).rem_euclid(values.len() as isize) as usize);
+    values[index].clone()
+}
@@ -0,0 +1,436 @@
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::{gameboard::{GameBoard, BoardRules, BoardDimensions, HoldRule, TopOutRule, BlockVisibility, BOARD_WIDTH, BOARD_HEIGHT}, master_mode::MasterMode, puzzle_mode::{Puzzle, PuzzleMode}, piece_factory::{PieceSet, PieceFactory}, scoring::ScoringKind};
+
+pub const SPEED_FACTOR: f32 = 0.8;
+pub const MIN_LOCK_DELAY: f32 = 0.4;
+
+// Gravities are usually given in G, rows fallen per frame at this rate
+pub const FRAME_RATE: f32 = 60.0;
+// From 20G on pieces reach the floor as soon as they spawn
+pub const INSTANT_GRAVITY: f32 = 20.0 * FRAME_RATE;
+
+const MARATHON_FALL_TIME: f32 = 1.0 / 4.0;
+const MARATHON_LINES_PER_LEVEL: u32 = 2;
+
+const BIG_CELL_SIZE: usize = 2;
+
+const PENTOMINO_BOARD_WIDTH: usize = 12;
+const PENTOMINO_BOARD_HEIGHT: usize = 24;
+
+pub trait GameMode {
+    fn get_rules(&self) -> BoardRules;
+
+    fn get_dimensions(&self) -> BoardDimensions {
+        BoardDimensions::default()
+    }
+
+    // Lets the mode lay out the board before the first piece is played
+    fn prepare(&self, _board: &mut GameBoard) {}
+
+    // Pieces dealt by the mode, None for the tetrominoes
+    fn get_piece_set(&self) -> Option<PieceSet> {
+        None
+    }
+
+    // Scoring used unless the player picks another one
+    fn get_scoring(&self) -> ScoringKind;
+
+    // Rows fallen per second, None when gravity is disabled
+    fn get_gravity(&self) -> Option<f32>;
+
+    // Seconds a grounded piece waits before locking, None for an unlimited delay
+    fn get_lock_delay(&self) -> Option<f32>;
+
+    // Called every frame so the mode can follow the board progression
+    fn update(&mut self, board: &mut GameBoard, time_delta: f32);
+
+    fn is_finished(&self) -> bool {
+        false
+    }
+
+    // Text shown once the game is over, e.g. a grade
+    fn get_result(&self) -> Option<String> {
+        None
+    }
+}
+
+#[derive(EnumIter, Copy, Clone, PartialEq, Debug)]
+pub enum ModeKind {
+    Marathon,
+    Zen,
+    Master,
+    Big,
+    Pentomino,
+    Puzzle
+}
+
+impl ModeKind {
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            ModeKind::Marathon => "marathon",
+            ModeKind::Zen => "zen",
+            ModeKind::Master => "master",
+            ModeKind::Big => "big",
+            ModeKind::Pentomino => "pentomino",
+            ModeKind::Puzzle => "puzzle"
+        }
+    }
+
+
+    pub fn from_name(name: &str) -> Option<ModeKind> {
+        ModeKind::iter().find(|kind| kind.get_name() == name)
+    }
+
+
+    pub fn create(&self, options: &ModeOptions) -> Box<dyn GameMode> {
+        match self {
+            ModeKind::Marathon => Box::new(MarathonMode::new()),
+            ModeKind::Zen => Box::new(ZenMode::new(options.infinite_hold)),
+            ModeKind::Master => Box::new(MasterMode::new()),
+            ModeKind::Big => Box::new(BigMode::new()),
+            ModeKind::Pentomino => Box::new(PentominoMode::new()),
+            ModeKind::Puzzle => Box::new(PuzzleMode::new(options.puzzle.clone().unwrap_or_default()))
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ModeOptions {
+    pub infinite_hold: bool,
+    // Challenge modifier applied on top of any mode
+    pub visibility: BlockVisibility,
+    pub puzzle: Option<Puzzle>,
+    // Replaces the tetrominoes of the mode
+    pub piece_set: Option<PieceSet>,
+    // Replaces the scoring of the mode
+    pub scoring: Option<ScoringKind>
+}
+
+impl Default for ModeOptions {
+    fn default() -> Self {
+        ModeOptions {
+            infinite_hold: false,
+            visibility: BlockVisibility::Visible,
+            puzzle: None,
+            piece_set: None,
+            scoring: None
+        }
+    }
+}
+
+
+pub struct MarathonMode {
+    fall_time: f32,
+    lock_delay: f32,
+    level: u32,
+    hold_lines_cleared: u32
+}
+
+impl MarathonMode {
+
+    pub fn new() -> Self {
+        MarathonMode {
+            fall_time: MARATHON_FALL_TIME,
+            lock_delay: 1.5 * MARATHON_FALL_TIME,
+            level: 1,
+            hold_lines_cleared: 0
+        }
+    }
+}
+
+impl GameMode for MarathonMode {
+
+    fn get_rules(&self) -> BoardRules {
+        BoardRules::default()
+    }
+
+
+    // Keeps the points of the original game: 100/300/500/800 times the level, and 2 points a hard dropped row
+    fn get_scoring(&self) -> ScoringKind {
+        ScoringKind::Guideline
+    }
+
+
+    fn get_gravity(&self) -> Option<f32> {
+        Some(1.0 / self.fall_time)
+    }
+
+
+    fn get_lock_delay(&self) -> Option<f32> {
+        Some(self.lock_delay)
+    }
+
+
+    fn update(&mut self, board: &mut GameBoard, _time_delta: f32) {
+        let cleared = board.get_lines_cleared();
+        if cleared.is_multiple_of(MARATHON_LINES_PER_LEVEL) &&
+        self.hold_lines_cleared != cleared {
+            self.level += 1;
+            board.set_level(self.level);
+            self.fall_time *= SPEED_FACTOR;
+            self.lock_delay *= SPEED_FACTOR + (1.0 - SPEED_FACTOR) / 2.0;
+            self.lock_delay = f32::min(self.lock_delay, MIN_LOCK_DELAY);
+            self.hold_lines_cleared = cleared;
+        }
+    }
+}
+
+
+// Marathon played with 2x2 blocks, the logic runs on a grid half the size of the drawn one
+pub struct BigMode {
+    marathon: MarathonMode
+}
+
+impl BigMode {
+
+    pub fn new() -> Self {
+        BigMode {
+            marathon: MarathonMode::new()
+        }
+    }
+}
+
+impl GameMode for BigMode {
+
+    fn get_rules(&self) -> BoardRules {
+        self.marathon.get_rules()
+    }
+
+
+    fn get_scoring(&self) -> ScoringKind {
+        self.marathon.get_scoring()
+    }
+
+
+    fn get_dimensions(&self) -> BoardDimensions {
+        BoardDimensions {
+            width: BOARD_WIDTH / BIG_CELL_SIZE,
+            height: BOARD_HEIGHT / BIG_CELL_SIZE,
+            cell_size: BIG_CELL_SIZE
+        }
+    }
+
+
+    fn get_gravity(&self) -> Option<f32> {
+        self.marathon.get_gravity()
+    }
+
+
+    fn get_lock_delay(&self) -> Option<f32> {
+        self.marathon.get_lock_delay()
+    }
+
+
+    fn update(&mut self, board: &mut GameBoard, time_delta: f32) {
+        self.marathon.update(board, time_delta);
+    }
+}
+
+
+// Marathon with the 18 pentominoes on a board wide enough for them
+pub struct PentominoMode {
+    marathon: MarathonMode
+}
+
+impl PentominoMode {
+
+    pub fn new() -> Self {
+        PentominoMode {
+            marathon: MarathonMode::new()
+        }
+    }
+}
+
+impl GameMode for PentominoMode {
+
+    fn get_rules(&self) -> BoardRules {
+        self.marathon.get_rules()
+    }
+
+
+    fn get_scoring(&self) -> ScoringKind {
+        self.marathon.get_scoring()
+    }
+
+
+    fn get_dimensions(&self) -> BoardDimensions {
+        BoardDimensions {
+            width: PENTOMINO_BOARD_WIDTH,
+            height: PENTOMINO_BOARD_HEIGHT,
+            cell_size: 1
+        }
+    }
+
+
+    fn get_piece_set(&self) -> Option<PieceSet> {
+        Some(PieceFactory::pentominoes())
+    }
+
+
+    fn get_gravity(&self) -> Option<f32> {
+        self.marathon.get_gravity()
+    }
+
+
+    fn get_lock_delay(&self) -> Option<f32> {
+        self.marathon.get_lock_delay()
+    }
+
+
+    fn update(&mut self, board: &mut GameBoard, time_delta: f32) {
+        self.marathon.update(board, time_delta);
+    }
+}
+
+
+// Practice mode: no gravity, no lock delay and the board is emptied on top out
+pub struct ZenMode {
+    infinite_hold: bool
+}
+
+impl ZenMode {
+
+    pub fn new(infinite_hold: bool) -> Self {
+        ZenMode {
+            infinite_hold
+        }
+    }
+}
+
+impl GameMode for ZenMode {
+
+    fn get_rules(&self) -> BoardRules {
+        BoardRules {
+            hold: match self.infinite_hold {
+                true => HoldRule::Unlimited,
+                false => HoldRule::OncePerPiece
+            },
+            top_out: TopOutRule::ClearBoard,
+            ..BoardRules::default()
+        }
+    }
+
+
+    fn get_scoring(&self) -> ScoringKind {
+        ScoringKind::Guideline
+    }
+
+
+    fn get_gravity(&self) -> Option<f32> {
+        None
+    }
+
+
+    fn get_lock_delay(&self) -> Option<f32> {
+        None
+    }
+
+
+    fn update(&mut self, _board: &mut GameBoard, _time_delta: f32) {}
+}
+
+
+#[cfg(test)]
+mod test {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use strum::IntoEnumIterator;
+
+    use crate::{gameboard::{GameBoard, MovementDirection, TopOutRule, HoldRule, BOARD_WIDTH}, piece_provider::PieceProvider, scoring::ScoringKind};
+
+    use super::{ModeKind, ModeOptions, ZenMode, GameMode};
+
+    #[test]
+    fn every_mode_kind_is_found_back_from_its_name() {
+        for kind in ModeKind::iter() {
+            assert_eq!(ModeKind::from_name(kind.get_name()), Some(kind));
+        }
+    }
+
+
+    #[test]
+    fn every_mode_has_its_own_scoring() {
+        let expected = [
+            (ModeKind::Marathon, ScoringKind::Guideline),
+            (ModeKind::Zen, ScoringKind::Guideline),
+            (ModeKind::Master, ScoringKind::Sega),
+            (ModeKind::Big, ScoringKind::Guideline),
+            (ModeKind::Pentomino, ScoringKind::Guideline),
+            (ModeKind::Puzzle, ScoringKind::Guideline)
+        ];
+
+        for (kind, scoring) in expected {
+            assert_eq!(kind.create(&ModeOptions::default()).get_scoring(), scoring);
+        }
+    }
+
+
+    #[test]
+    fn zen_mode_has_no_gravity_nor_lock_delay() {
+        let sut = ModeKind::Zen.create(&ModeOptions::default());
+
+        assert!(sut.get_gravity().is_none());
+        assert!(sut.get_lock_delay().is_none());
+        assert_eq!(sut.get_rules().top_out, TopOutRule::ClearBoard);
+    }
+
+
+    #[test]
+    fn zen_mode_can_hold_several_times_with_infinite_hold() {
+        let sut = ZenMode::new(true);
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let mut board = GameBoard::new(rng, 1);
+        board.set_rules(sut.get_rules());
+
+        assert_eq!(sut.get_rules().hold, HoldRule::Unlimited);
+        for _ in 0..3 {
+            assert!(board.try_swap().is_ok());
+        }
+    }
+
+
+    #[test]
+    fn zen_mode_keeps_playing_after_topping_out() {
+        let sut = ZenMode::new(false);
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let mut board = GameBoard::new(rng, 1);
+        board.set_rules(sut.get_rules());
+
+        for _ in 0..50 {
+            let _ = board.try_move(MovementDirection::Top);
+            board.lock_current_piece();
+        }
+
+        assert!(board.keep_playing());
+    }
+
+
+    #[test]
+    fn big_mode_plays_on_a_grid_of_2x2_cells() {
+        let sut = ModeKind::Big.create(&ModeOptions::default());
+        let dimensions = sut.get_dimensions();
+
+        assert_eq!((dimensions.width, dimensions.height), (5, 11));
+        assert_eq!(dimensions.cell_size, 2);
+    }
+
+
+    #[test]
+    fn pentomino_mode_deals_pentominoes_on_a_wider_board() {
+        let sut = ModeKind::Pentomino.create(&ModeOptions::default());
+        let mut board = GameBoard::new(ChaCha8Rng::seed_from_u64(1), 1);
+        board.set_dimensions(sut.get_dimensions());
+        board.set_piece_provider(PieceProvider::with_piece_set(sut.get_piece_set().unwrap(), ChaCha8Rng::seed_from_u64(1)));
+
+        assert!(sut.get_dimensions().width > BOARD_WIDTH);
+        for _ in 0..5 {
+            assert_eq!(board.get_current_piece().get_squares().len(), 5);
+            assert!(board.get_current_piece().get_squares().iter().all(|square| board.is_free(square.get_position())));
+            let _ = board.try_move(MovementDirection::Top);
+            board.lock_current_piece();
+        }
+    }
+}
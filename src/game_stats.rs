@@ -0,0 +1,193 @@
+use crate::{game_event::GameEvent, gameboard::BoardDimensions, finesse::FinesseTracker};
+
+const CLEAR_NAMES: [&str; 4] = ["Singles", "Doubles", "Triples", "Tetrises"];
+
+// Statistics of a game, built only from the events of the board
+pub struct GameStats {
+    elapsed_time: f32,
+    pieces_placed: u32,
+    keys_pressed: u32,
+    lines_cleared: u32,
+    // Clears of 1, 2, 3 and 4 lines or more
+    clears: [u32; 4],
+    t_spins: u32,
+    holds: u32,
+    clear_streak: u32,
+    max_combo: u32,
+    last_lock_cleared: bool,
+    finesse: FinesseTracker
+}
+
+impl GameStats {
+
+    pub fn new(dimensions: BoardDimensions) -> Self {
+        GameStats {
+            elapsed_time: 0.0,
+            pieces_placed: 0,
+            keys_pressed: 0,
+            lines_cleared: 0,
+            clears: [0; 4],
+            t_spins: 0,
+            holds: 0,
+            clear_streak: 0,
+            max_combo: 0,
+            last_lock_cleared: false,
+            finesse: FinesseTracker::new(dimensions)
+        }
+    }
+
+
+    pub fn advance_time(&mut self, delta: f32) {
+        self.elapsed_time += delta;
+    }
+
+
+    pub fn record(&mut self, event: &GameEvent) {
+        self.finesse.record(event);
+        match event {
+            GameEvent::Input(_) => self.keys_pressed += 1,
+            GameEvent::Held => self.holds += 1,
+            GameEvent::Locked(_) => {
+                self.pieces_placed += 1;
+                // A lock without clear ends the combo
+                if !self.last_lock_cleared {
+                    self.clear_streak = 0;
+                }
+                self.last_lock_cleared = false;
+            },
+            GameEvent::LinesCleared { rows, kind } => {
+                if kind.t_spin {
+                    self.t_spins += 1;
+                }
+                if rows.is_empty() {
+                    return;
+                }
+                self.lines_cleared += rows.len() as u32;
+                self.clears[usize::min(rows.len(), self.clears.len()) - 1] += 1;
+                self.clear_streak += 1;
+                self.max_combo = u32::max(self.max_combo, self.clear_streak - 1);
+                self.last_lock_cleared = true;
+            },
+            _ => ()
+        }
+    }
+
+
+    pub fn get_pieces_per_second(&self) -> f32 {
+        match self.elapsed_time > 0.0 {
+            true => self.pieces_placed as f32 / self.elapsed_time,
+            false => 0.0
+        }
+    }
+
+
+    pub fn get_keys_per_piece(&self) -> f32 {
+        match self.pieces_placed > 0 {
+            true => self.keys_pressed as f32 / self.pieces_placed as f32,
+            false => 0.0
+        }
+    }
+
+
+    pub fn get_lines_per_minute(&self) -> f32 {
+        match self.elapsed_time > 0.0 {
+            true => self.lines_cleared as f32 * 60.0 / self.elapsed_time,
+            false => 0.0
+        }
+    }
+
+
+    // Label and value of every statistic, in display order
+    pub fn get_summary(&self) -> Vec<(&'static str, String)> {
+        let mut summary = vec![
+            ("Pieces", self.pieces_placed.to_string()),
+            ("PPS", format!("{:.2}", self.get_pieces_per_second())),
+            ("KPP", format!("{:.2}", self.get_keys_per_piece())),
+            ("LPM", format!("{:.1}", self.get_lines_per_minute()))
+        ];
+        for (name, count) in CLEAR_NAMES.iter().zip(self.clears.iter()) {
+            summary.push((name, count.to_string()));
+        }
+        summary.push(("T-spins", self.t_spins.to_string()));
+        summary.push(("Max combo", self.max_combo.to_string()));
+        summary.push(("Holds", self.holds.to_string()));
+        summary.push(("Finesse", self.finesse.get_faults().to_string()));
+        summary
+    }
+
+
+    // Shown while the last placed piece took more inputs than needed
+    pub fn get_finesse_feedback(&self) -> Option<String> {
+        self.finesse.get_last_fault()
+            .map(|fault| format!("{} inputs, {} needed", fault.used, fault.needed))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{game_event::GameEvent, gameboard::{LineClear, MovementDirection, BoardDimensions, Action}, piece::Piece, piece_factory::PieceType};
+
+    use super::GameStats;
+
+    fn lock(stats: &mut GameStats, lines: usize, t_spin: bool) {
+        stats.record(&GameEvent::Locked(Piece::from(PieceType::T)));
+        if lines > 0 || t_spin {
+            stats.record(&GameEvent::LinesCleared {
+                rows: (0..lines).collect(),
+                kind: LineClear { lines: lines as u32, t_spin, perfect_clear: false, back_to_back: false }
+            });
+        }
+    }
+
+    #[test]
+    fn clears_are_counted_by_kind() {
+        let mut sut = GameStats::new(BoardDimensions::default());
+
+        lock(&mut sut, 1, false);
+        lock(&mut sut, 4, false);
+        lock(&mut sut, 2, true);
+        lock(&mut sut, 0, true);
+
+        assert_eq!(sut.clears, [1, 1, 0, 1]);
+        assert_eq!(sut.t_spins, 2);
+        assert_eq!(sut.lines_cleared, 7);
+    }
+
+
+    #[test]
+    fn max_combo_counts_consecutive_clearing_locks() {
+        let mut sut = GameStats::new(BoardDimensions::default());
+
+        lock(&mut sut, 1, false);
+        lock(&mut sut, 1, false);
+        lock(&mut sut, 1, false);
+        lock(&mut sut, 0, false);
+        lock(&mut sut, 1, false);
+        lock(&mut sut, 1, false);
+
+        assert_eq!(sut.max_combo, 2);
+    }
+
+
+    #[test]
+    fn rates_follow_time_and_inputs() {
+        let mut sut = GameStats::new(BoardDimensions::default());
+        assert_eq!(sut.get_pieces_per_second(), 0.0);
+
+        // Presses count even when the piece cannot move
+        for _ in 0..3 {
+            sut.record(&GameEvent::Input(Action::Move(MovementDirection::Left)));
+        }
+        sut.record(&GameEvent::Input(Action::Hold));
+        sut.record(&GameEvent::Held);
+        lock(&mut sut, 2, false);
+        lock(&mut sut, 0, false);
+        sut.advance_time(2.0);
+
+        assert_eq!(sut.get_pieces_per_second(), 1.0);
+        assert_eq!(sut.get_keys_per_piece(), 2.0);
+        assert_eq!(sut.get_lines_per_minute(), 60.0);
+        assert_eq!(sut.holds, 1);
+    }
+}
@@ -3,38 +3,157 @@ use std::collections::BTreeSet;
 use std::{collections::VecDeque};
 use std::fmt::{Debug, Write};
 
-use kiss3d::nalgebra::Vector2;
+use nalgebra::Vector2;
 
 use rand_chacha::ChaCha8Rng;
 
 
-use crate::{piece::{Square, Piece}, piece_provider::PieceProvider, piece_factory::{PieceType, SPAWN_POINT}};
+use crate::{piece::{Square, Piece, RotationType}, piece_provider::PieceProvider, piece_factory::{PieceType, SPAWN_POINT}, scoring::{ScoringSystem, GuidelineScoring}, game_event::{GameEvent, GameOverReason}};
 
 pub const BOARD_WIDTH: usize = 10;
 pub const BOARD_HEIGHT: usize = 22;
 
-const PLAY_POINT: Vector2<isize> = Vector2::new(4, 20);
-
-const SOFT_DROP_SCORE: u32 = 1;
-const HARD_DROP_SCORE: u32 = 2;
-
 const PIECE_QUEUE_SIZE: usize = 5;
 
 pub struct GameBoard {
-    square_board: [[Option<Square>; BOARD_HEIGHT]; BOARD_WIDTH],
-    space_board: [[bool; BOARD_HEIGHT]; BOARD_WIDTH],
+    dimensions: BoardDimensions,
+    square_board: Vec<Vec<Option<Square>>>,
+    space_board: Vec<Vec<bool>>,
     piece_provider: PieceProvider,
     current_piece: Piece,
     held_piece: Option<Piece>,
     can_swap: bool,
     next_pieces: VecDeque<Piece>,
     score: u32,
+    scoring: Box<dyn ScoringSystem>,
     level: u32,
     lines_cleared: u32,
-    game_over: bool
+    pieces_placed: u32,
+    elapsed_time: f32,
+    rules: BoardRules,
+    last_move_rotation: bool,
+    last_clear: Option<LineClear>,
+    // The last line clear was a tetris or a T-spin
+    difficult_clear: bool,
+    events: Vec<GameEvent>,
+    game_over: bool,
+    // Set when the mode reached its goal rather than the stack its top
+    finished: bool
+}
+
+// Outcome of a lock that cleared lines or was a T-spin
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LineClear {
+    pub lines: u32,
+    pub t_spin: bool,
+    pub perfect_clear: bool,
+    // A tetris or a T-spin clear following another one with no easier clear between
+    pub back_to_back: bool
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum HoldRule {
+    OncePerPiece,
+    Unlimited,
+    Disabled
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TopOutRule {
+    GameOver,
+    ClearBoard
+}
+
+// Only read by visualizers, locked squares keep colliding whatever their visibility
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BlockVisibility {
+    Visible,
+    Fading(f32),
+    Invisible
+}
+
+impl BlockVisibility {
+
+    pub fn is_visible(&self, square: &Square, now: f32) -> bool {
+        match (self, square.get_lock_time()) {
+            (_, None) => true,
+            (BlockVisibility::Visible, _) => true,
+            (BlockVisibility::Fading(duration), Some(lock_time)) => now - lock_time < *duration,
+            (BlockVisibility::Invisible, _) => false
+        }
+    }
+
+
+    // Either visible, invisible or fading:<seconds>
+    pub fn from_name(name: &str) -> Option<BlockVisibility> {
+        match name.split_once(':') {
+            Some(("fading", seconds)) => seconds.parse::<f32>().ok().map(BlockVisibility::Fading),
+            Some(_) => None,
+            None => match name {
+                "visible" => Some(BlockVisibility::Visible),
+                "invisible" => Some(BlockVisibility::Invisible),
+                _ => None
+            }
+        }
+    }
+
+
+    pub fn get_name(&self) -> String {
+        match self {
+            BlockVisibility::Visible => String::from("visible"),
+            BlockVisibility::Fading(seconds) => format!("fading:{}", seconds),
+            BlockVisibility::Invisible => String::from("invisible")
+        }
+    }
+}
+
+// Size of the logical grid, each logical cell being drawn as a cell_size wide square
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BoardDimensions {
+    pub width: usize,
+    pub height: usize,
+    pub cell_size: usize
+}
+
+impl BoardDimensions {
+
+    pub fn get_play_point(&self) -> Vector2<isize> {
+        Vector2::new(self.width as isize / 2 - 1, self.height as isize - 2)
+    }
+}
+
+impl Default for BoardDimensions {
+    fn default() -> Self {
+        BoardDimensions {
+            width: BOARD_WIDTH,
+            height: BOARD_HEIGHT,
+            cell_size: 1
+        }
+    }
 }
 
-#[derive(Copy, Clone)]
+// Rules a game mode can change without touching the board logic
+#[derive(Copy, Clone, Debug)]
+pub struct BoardRules {
+    pub hold: HoldRule,
+    pub top_out: TopOutRule,
+    pub visibility: BlockVisibility,
+    // Levels are announced when they reach a new group of this many levels
+    pub level_section: u32
+}
+
+impl Default for BoardRules {
+    fn default() -> Self {
+        BoardRules {
+            hold: HoldRule::OncePerPiece,
+            top_out: TopOutRule::GameOver,
+            visibility: BlockVisibility::Visible,
+            level_section: 1
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum MovementDirection {
     Right,
     Top,
@@ -46,14 +165,15 @@ pub enum MovementDirection {
 enum PossibleRotation {
     None, 
     Regular, 
-    Right,
-    Left
+    // Index of the offset in the kick table of the piece
+    Kicked(usize)
 }
 
 unsafe impl Send for MovementDirection {}
 unsafe impl Sync for MovementDirection {}
 
 
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum Action {
     Move(MovementDirection),
     Rotate,
@@ -71,26 +191,37 @@ pub struct SwapError;
 impl GameBoard {
 
     pub fn new(rng: ChaCha8Rng, level: u32) -> Self {
+        let dimensions = BoardDimensions::default();
         let mut piece_provider = PieceProvider::new(rng);
-        let mut current_piece = piece_provider.get_piece();
-        current_piece.move_at(PLAY_POINT);
+        let mut current_piece = piece_provider.get_piece().unwrap();
+        current_piece.move_at(dimensions.get_play_point());
         let mut next_pieces: VecDeque<Piece> = VecDeque::new();
         for _ in 0..PIECE_QUEUE_SIZE {
-            next_pieces.push_back(piece_provider.get_piece());
+            next_pieces.extend(piece_provider.get_piece());
         }
 
         GameBoard {
-            square_board: [[None; BOARD_HEIGHT]; BOARD_WIDTH],
-            space_board: [[false; BOARD_HEIGHT]; BOARD_WIDTH],
+            dimensions,
+            square_board: vec![vec![None; dimensions.height]; dimensions.width],
+            space_board: vec![vec![false; dimensions.height]; dimensions.width],
             piece_provider,
             current_piece,
             held_piece: None,
             can_swap: true,
             next_pieces,
             score: 0,
+            scoring: Box::new(GuidelineScoring::new()),
             level,
             lines_cleared: 0,
-            game_over: false
+            pieces_placed: 0,
+            elapsed_time: 0.0,
+            rules: BoardRules::default(),
+            last_move_rotation: false,
+            last_clear: None,
+            difficult_clear: false,
+            events: vec![],
+            game_over: false,
+            finished: false
         }
     }
 
@@ -100,8 +231,13 @@ impl GameBoard {
     }
 
 
-    pub fn get_square_board(&self) -> [[Option<Square>; BOARD_HEIGHT]; BOARD_WIDTH] {
-        self.square_board.clone()
+    pub fn get_square_board(&self) -> &Vec<Vec<Option<Square>>> {
+        &self.square_board
+    }
+
+
+    pub fn get_dimensions(&self) -> BoardDimensions {
+        self.dimensions
     }
 
 
@@ -115,6 +251,17 @@ impl GameBoard {
     }
 
 
+    pub fn get_pieces_placed(&self) -> u32 {
+        self.pieces_placed
+    }
+
+
+    // Shown squares are the current piece and the locked squares the rules let appear
+    pub fn is_square_shown(&self, square: &Square) -> bool {
+        !self.keep_playing() || self.rules.visibility.is_visible(square, self.elapsed_time)
+    }
+
+
     pub fn get_level(&self) -> u32 {
         self.level
     }
@@ -125,23 +272,85 @@ impl GameBoard {
     }
 
 
-    pub fn get_next_piece(&self) -> &Piece {
-        &self.next_pieces.front().unwrap()
+    pub fn get_next_pieces(&self) -> &VecDeque<Piece> {
+        &self.next_pieces
+    }
+
+
+    // Where the current piece would land if hard dropped
+    pub fn get_ghost_piece(&self) -> Piece {
+        let mut ghost = self.current_piece.clone();
+        let below = Vector2::new(0, -1);
+        while ghost.get_squares().iter().all(|square| self.is_free(square.get_position() + below)) {
+            ghost.translate(below);
+        }
+        ghost
+    }
+
+
+    pub fn get_last_clear(&self) -> Option<LineClear> {
+        self.last_clear
     }
 
 
     pub fn set_level(&mut self, value: u32) {
+        if value / self.rules.level_section > self.level / self.rules.level_section {
+            self.events.push(GameEvent::LevelUp(value));
+        }
         self.level = value;
     }
 
 
+    pub fn set_rules(&mut self, value: BoardRules) {
+        self.rules = value;
+    }
+
+
+    pub fn set_scoring(&mut self, value: Box<dyn ScoringSystem>) {
+        self.scoring = value;
+    }
+
+
+    // Replaces the queue, the current piece included
+    pub fn set_piece_provider(&mut self, value: PieceProvider) {
+        self.piece_provider = value;
+        self.next_pieces.clear();
+        for _ in 0..=PIECE_QUEUE_SIZE {
+            self.next_pieces.extend(self.piece_provider.get_piece());
+        }
+        self.draw();
+    }
+
+
+    // Used to build a starting layout, the square is considered locked at the start
+    pub fn place_square(&mut self, mut square: Square) {
+        let position = square.get_position();
+        square.set_lock_time(0.0);
+        self.space_board[position.x as usize][position.y as usize] = true;
+        self.square_board[position.x as usize][position.y as usize] = Some(square);
+    }
+
+
+    // Resizing empties the board, it is meant to be done before playing
+    pub fn set_dimensions(&mut self, value: BoardDimensions) {
+        self.dimensions = value;
+        self.clear_board();
+        self.current_piece.move_at(value.get_play_point());
+    }
+
+
+    pub fn advance_time(&mut self, delta: f32) {
+        self.elapsed_time += delta;
+    }
+
+
     pub fn is_free(&self, position: Vector2<isize>) -> bool {
 
-        if position.x < 0 || position.x >= BOARD_WIDTH as isize {
+        if position.x < 0 || position.x >= self.dimensions.width as isize {
             return false;
         }
 
-        if position.y < 0 || position.y >= BOARD_HEIGHT as isize {
+        if position.y < 0 || position.y >= self.dimensions.height as isize {
             return false;
         }
 
@@ -165,7 +374,7 @@ impl GameBoard {
 
 
     fn can_rotate(&self) -> PossibleRotation {
-        if self.current_piece.get_piece_type() == PieceType::Square {
+        if self.current_piece.get_rotation_type() == RotationType::None {
             return PossibleRotation::None;
         }
 
@@ -183,23 +392,14 @@ impl GameBoard {
             return PossibleRotation::Regular;
         }
 
-        let can_rotate_right = rotated_positions.iter().all(|position| {
-            self.is_free(*position + Vector2::new(1,0))
+        let kick_index = self.current_piece.get_kicks().iter().position(|kick| {
+            rotated_positions.iter().all(|position| self.is_free(*position + kick))
         });
 
-        if can_rotate_right {
-            return PossibleRotation::Right;
+        match kick_index {
+            Some(index) => PossibleRotation::Kicked(index),
+            None => PossibleRotation::None
         }
-
-        let can_rotate_right = rotated_positions.iter().all(|position| {
-            self.is_free(*position + Vector2::new(-1,0))
-        });
-
-        if can_rotate_right {
-            return PossibleRotation::Left;
-        }
-       
-        PossibleRotation::None
     }
 
 
@@ -221,28 +421,78 @@ impl GameBoard {
     }
 
 
+    pub fn is_grounded(&self) -> bool {
+        !self.can_fall()
+    }
+
+
     pub fn keep_playing(&self) -> bool {
-        !self.game_over
+        !self.game_over && !self.finished
+    }
+
+
+    // Ends the game without a top out, once the mode is done
+    pub fn finish(&mut self) {
+        self.finished = true;
     }
 
 
     fn initialize_piece_position(&mut self) {
-        self.current_piece.translate(PLAY_POINT - SPAWN_POINT)
+        self.current_piece.translate(self.dimensions.get_play_point() - SPAWN_POINT)
+    }
+
+
+    // Inputs are reported among the board events so they keep their order
+    pub fn report_input(&mut self, action: Action) {
+        self.events.push(GameEvent::Input(action));
+    }
+
+
+    // Events emitted since the last call, oldest first
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+
+    fn end_game(&mut self, reason: GameOverReason) {
+        self.game_over = true;
+        self.events.push(GameEvent::GameOver(reason));
     }
 
 
     fn draw(&mut self){
-        self.current_piece = self.next_pieces.pop_front().unwrap();
-        self.next_pieces.push_back(self.piece_provider.get_piece());
+        match self.next_pieces.pop_front() {
+            Some(piece) => self.current_piece = piece,
+            None => {
+                self.end_game(GameOverReason::EmptyQueue);
+                return;
+            }
+        }
+        self.next_pieces.extend(self.piece_provider.get_piece());
 
         self.initialize_piece_position();
-        self.game_over = !self.can_spawn();
+        if !self.can_spawn() {
+            match self.rules.top_out {
+                TopOutRule::GameOver => {
+                    self.end_game(GameOverReason::TopOut);
+                    return;
+                },
+                TopOutRule::ClearBoard => self.clear_board()
+            }
+        }
+        self.events.push(GameEvent::PieceSpawned(self.current_piece.get_piece_type()));
+    }
+
+
+    fn clear_board(&mut self) {
+        self.square_board = vec![vec![None; self.dimensions.height]; self.dimensions.width];
+        self.space_board = vec![vec![false; self.dimensions.height]; self.dimensions.width];
     }
 
 
     fn get_first_free_line_from(&mut self, index: usize) -> usize {
-        let mut result = BOARD_HEIGHT-1;
-        for i in index..BOARD_HEIGHT {
+        let mut result = self.dimensions.height-1;
+        for i in index..self.dimensions.height {
             if self.space_board.iter().all(|column| !column[i]) {
                 result = i;
                 break;
@@ -254,7 +504,7 @@ impl GameBoard {
 
     fn clear_line(&mut self, destroy_index: usize){
         let limit = self.get_first_free_line_from(destroy_index);
-        for i in 0..BOARD_WIDTH {
+        for i in 0..self.dimensions.width {
             for line_index in destroy_index..limit {
                 self.space_board[i][line_index] = self.space_board[i][line_index+1];
                 self.square_board[i][line_index] = self.square_board[i][line_index+1];
@@ -265,52 +515,87 @@ impl GameBoard {
     }
 
 
+    // A T-spin is a T locked right after a rotation with three of its corners occupied,
+    // only the tetromino T having corners to count
+    fn is_t_spin(&self) -> bool {
+        let is_t = self.current_piece.get_piece_type() == PieceType::T && self.current_piece.has_shape_of(&Piece::from(PieceType::T));
+        if !is_t || !self.last_move_rotation {
+            return false;
+        }
+
+        let center = self.current_piece.get_squares()[0].get_position();
+        let occupied_corners = [(-1, -1), (-1, 1), (1, -1), (1, 1)].iter().filter(|(x, y)| {
+            !self.is_free(center + Vector2::new(*x, *y))
+        }).count();
+        occupied_corners >= 3
+    }
+
+
+    fn is_empty(&self) -> bool {
+        self.space_board.iter().all(|column| column.iter().all(|filled| !filled))
+    }
+
+
     // Usage of BTreeSet allows the collection to be sorted for destruction
-    fn check_complete_line(&mut self, line_indexes: BTreeSet<usize>) {
-        let mut cleared = 0;
+    fn check_complete_line(&mut self, line_indexes: BTreeSet<usize>) -> Vec<usize> {
+        let mut cleared = vec![];
         for line_index in line_indexes.iter().rev() {
             if self.space_board.iter().all(|column| column[*line_index]) {
                 self.clear_line(*line_index);
-                cleared += 1;
+                cleared.push(*line_index);
             }
         }
-
-        self.score += match cleared {
-            1 => 100 * self.level,
-            2 => 300 * self.level,
-            3 => 500 * self.level,
-            4 => 800 * self.level,
-            _ => 0
-        }
+        cleared
     }
 
 
     pub fn lock_current_piece(&mut self) {
         let mut modified_lines: BTreeSet<usize> = BTreeSet::new();
-        for square in self.current_piece.get_squares_owned() {
+        for mut square in self.current_piece.get_squares_owned() {
             let position = square.get_position();
+            square.set_lock_time(self.elapsed_time);
             self.space_board[position.x as usize][position.y as usize] = true;
             self.square_board[position.x as usize][position.y as usize] = Some(square);
             modified_lines.insert(position.y as usize);
         }
 
-        self.check_complete_line(modified_lines);
+        self.pieces_placed += 1;
+        let t_spin = self.is_t_spin();
+        let rows = self.check_complete_line(modified_lines);
+        let cleared = rows.len() as u32;
+        let difficult = cleared >= 4 || t_spin;
+        self.last_clear = (cleared > 0 || t_spin).then(|| LineClear {
+            lines: cleared,
+            t_spin,
+            perfect_clear: cleared > 0 && self.is_empty(),
+            back_to_back: cleared > 0 && difficult && self.difficult_clear
+        });
+        // T-spins without lines neither start nor break a back-to-back
+        if cleared > 0 {
+            self.difficult_clear = difficult;
+        }
+        self.score += self.scoring.score_lock(self.last_clear.as_ref(), self.level);
+        self.events.push(GameEvent::Locked(self.current_piece.clone()));
+        if let Some(clear) = self.last_clear {
+            self.events.push(GameEvent::LinesCleared { rows, kind: clear });
+        }
+        self.last_move_rotation = false;
         self.draw();
         self.can_swap = true;
     }
 
 
     fn swap_held_piece(&mut self) {
-        self.current_piece.to_initial_rotation();
+        self.current_piece.reset_rotation();
         match self.held_piece {
             Some(_) => {
-                self.current_piece.move_at(PLAY_POINT);
+                self.current_piece.move_at(self.dimensions.get_play_point());
                 std::mem::swap(&mut self.current_piece, self.held_piece.as_mut().unwrap())
             }
             None => {
                 // Weird but only way found for the moment
-                self.current_piece.move_at(PLAY_POINT);
-                let dummy_piece = Piece::from(PieceType::Square);
+                self.current_piece.move_at(self.dimensions.get_play_point());
+                let dummy_piece = Piece::from(PieceType::O);
                 self.held_piece = Some(dummy_piece);
                 std::mem::swap(&mut self.current_piece, self.held_piece.as_mut().unwrap());
                 self.draw();
@@ -320,7 +605,13 @@ impl GameBoard {
 
 
     pub fn try_swap(&mut self) -> Result<(), SwapError> {
-        self.can_swap.then(|| {
+        let allowed = match self.rules.hold {
+            HoldRule::OncePerPiece => self.can_swap,
+            HoldRule::Unlimited => true,
+            HoldRule::Disabled => false
+        };
+        allowed.then(|| {
+            self.events.push(GameEvent::Held);
             self.swap_held_piece();
             self.can_swap = false;
         }).ok_or(SwapError)
@@ -330,23 +621,33 @@ impl GameBoard {
     pub fn try_fall(&mut self) -> Result<(), FallError> {
         self.can_fall().then(|| {
             self.current_piece.translate(Vector2::new(0,-1));
+            self.last_move_rotation = false;
         }).ok_or(FallError)
     }
 
 
+    // Gravity above one row per frame moves the piece by several rows at once
+    pub fn try_fall_by(&mut self, rows: usize) -> Result<usize, FallError> {
+        let mut fallen = 0;
+        while fallen < rows && self.try_fall().is_ok() {
+            fallen += 1;
+        }
+        (fallen > 0).then_some(fallen).ok_or(FallError)
+    }
+
+
     pub fn try_rotate(&mut self) -> Result<(), RotateError> {
-        let _ = match self.can_rotate() {
-            PossibleRotation::Regular => self.current_piece.rotate(),
-            PossibleRotation::Right => {
-                self.current_piece.translate(Vector2::new(1,0));
-                self.current_piece.rotate();
-            },
-            PossibleRotation::Left => {
-                self.current_piece.translate(Vector2::new(-1,0));
-                self.current_piece.rotate();
+        let kick = match self.can_rotate() {
+            PossibleRotation::Regular => None,
+            PossibleRotation::Kicked(index) => {
+                self.current_piece.translate(self.current_piece.get_kicks()[index]);
+                Some(index)
             },
             PossibleRotation::None => return Err(RotateError)
         };
+        self.current_piece.rotate();
+        self.events.push(GameEvent::Rotated(kick));
+        self.last_move_rotation = true;
         Ok(())
     }
 
@@ -357,12 +658,14 @@ impl GameBoard {
             MovementDirection::Right  => self.current_piece.translate(Vector2::new(1, 0)),
             MovementDirection::Bottom => {
                 self.current_piece.translate(Vector2::new(0, -1));
-                self.score += SOFT_DROP_SCORE;
+                self.score += self.scoring.score_soft_drop(1);
             },
             MovementDirection::Top => {
-                while let Ok(_) = self.try_fall() {
-                    self.score += HARD_DROP_SCORE;
+                let mut rows = 0;
+                while self.try_fall().is_ok() {
+                    rows += 1;
                 }
+                self.score += self.scoring.score_hard_drop(rows);
             }
         }
     }
@@ -371,6 +674,8 @@ impl GameBoard {
     pub fn try_move(&mut self, direction: MovementDirection) -> Result<(), MoveError> {
         self.can_move(direction).then(|| {
             self.move_at(direction);
+            self.last_move_rotation = false;
+            self.events.push(GameEvent::Moved(direction));
         }).ok_or(MoveError)
     }
 
@@ -379,8 +684,8 @@ impl GameBoard {
 
 impl Debug for GameBoard {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for i in (0..BOARD_HEIGHT).rev() {
-            for j in 0..BOARD_WIDTH {
+        for i in (0..self.dimensions.height).rev() {
+            for j in 0..self.dimensions.width {
                 let value = self.square_board[j][i];
                 let string = match value {
                     Some(square) => format!("{:?}", square).chars().next().unwrap(),
@@ -399,6 +704,192 @@ impl Debug for GameBoard {
 
 #[cfg(test)]
 mod test {
-    
-    
-}
\ No newline at end of file
+    use nalgebra::Vector2;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{piece::{Square, Color}, piece_factory::{PieceType, PieceFactory, PieceSet}, piece_provider::PieceProvider, scoring::ScoringKind};
+
+    use crate::game_event::{GameEvent, GameOverReason};
+
+    use super::{GameBoard, BOARD_HEIGHT, BOARD_WIDTH, BoardRules, BoardDimensions, BlockVisibility, MovementDirection};
+
+    #[test]
+    fn falling_by_several_rows_stops_on_the_floor() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let mut sut = GameBoard::new(rng, 1);
+
+        let fallen = sut.try_fall_by(BOARD_HEIGHT).ok().unwrap();
+
+        assert!(fallen > 1);
+        assert!(sut.is_grounded());
+        assert!(sut.try_fall_by(BOARD_HEIGHT).is_err());
+    }
+
+
+    fn locked_squares(board: &GameBoard) -> Vec<Square> {
+        board.get_square_board().iter().flatten().flatten().copied().collect()
+    }
+
+
+    #[test]
+    fn fading_squares_disappear_after_their_duration() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let mut sut = GameBoard::new(rng, 1);
+        sut.set_rules(BoardRules { visibility: BlockVisibility::Fading(2.0), ..BoardRules::default() });
+
+        let _ = sut.try_move(MovementDirection::Top);
+        sut.lock_current_piece();
+        sut.advance_time(1.0);
+        assert!(locked_squares(&sut).iter().all(|square| sut.is_square_shown(square)));
+
+        sut.advance_time(1.5);
+        assert!(locked_squares(&sut).iter().all(|square| !sut.is_square_shown(square)));
+        assert!(sut.get_current_piece().get_squares().iter().all(|square| sut.is_square_shown(square)));
+    }
+
+
+    #[test]
+    fn invisible_squares_still_collide() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let mut sut = GameBoard::new(rng, 1);
+        sut.set_rules(BoardRules { visibility: BlockVisibility::Invisible, ..BoardRules::default() });
+
+        let _ = sut.try_move(MovementDirection::Top);
+        sut.lock_current_piece();
+
+        let square = locked_squares(&sut)[0];
+        assert!(!sut.is_square_shown(&square));
+        assert!(!sut.is_free(square.get_position()));
+    }
+
+
+    fn piece_positions(board: &GameBoard) -> Vec<Vector2<isize>> {
+        board.get_current_piece().get_squares().iter().map(|square| square.get_position()).collect()
+    }
+
+
+    #[test]
+    fn pieces_collide_with_the_borders_of_a_smaller_board() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let dimensions = BoardDimensions { width: 5, height: 11, cell_size: 2 };
+        let mut sut = GameBoard::new(rng, 1);
+        sut.set_dimensions(dimensions);
+
+        // The S piece spawns on columns 0 to 2 of the 5 columns
+        assert_eq!(piece_positions(&sut).iter().map(|position| position.x).min(), Some(0));
+        assert!(sut.try_move(MovementDirection::Left).is_err());
+
+        let mut moves = 0;
+        while sut.try_move(MovementDirection::Right).is_ok() {
+            moves += 1;
+        }
+        assert_eq!(moves, 2);
+        assert_eq!(piece_positions(&sut).iter().map(|position| position.x).max(), Some(4));
+
+        let fallen = sut.try_fall_by(BOARD_HEIGHT).ok().unwrap();
+        assert_eq!(fallen, 9);
+        assert_eq!(piece_positions(&sut).iter().map(|position| position.y).min(), Some(0));
+    }
+
+
+    #[test]
+    fn board_reports_moves_locks_and_spawns_in_order() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let mut sut = GameBoard::new(rng, 1);
+        sut.drain_events();
+
+        let _ = sut.try_move(MovementDirection::Left);
+        let _ = sut.try_move(MovementDirection::Top);
+        sut.lock_current_piece();
+        sut.set_level(2);
+        let events = sut.drain_events();
+
+        assert_eq!(events[0], GameEvent::Moved(MovementDirection::Left));
+        assert_eq!(events[1], GameEvent::Moved(MovementDirection::Top));
+        assert!(matches!(events[2], GameEvent::Locked(_)));
+        assert!(matches!(events[3], GameEvent::PieceSpawned(_)));
+        assert_eq!(events[4], GameEvent::LevelUp(2));
+        assert!(sut.drain_events().is_empty());
+    }
+
+
+    #[test]
+    fn topping_out_reports_the_end_of_the_game() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let mut sut = GameBoard::new(rng, 1);
+
+        while sut.keep_playing() {
+            let _ = sut.try_move(MovementDirection::Top);
+            sut.lock_current_piece();
+        }
+
+        assert_eq!(sut.drain_events().last(), Some(&GameEvent::GameOver(GameOverReason::TopOut)));
+    }
+
+
+    #[test]
+    fn tetrises_in_a_row_are_back_to_back() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let mut sut = GameBoard::new(rng, 1);
+        sut.set_piece_provider(PieceProvider::from_sequence(vec![PieceType::I; 4]));
+        // Eight rows full but for the right column
+        for x in 0..BOARD_WIDTH as isize - 1 {
+            for y in 0..8 {
+                sut.place_square(Square::new(Vector2::new(x, y), Color::Gray));
+            }
+        }
+        sut.drain_events();
+
+        let mut clears = vec![];
+        for _ in 0..2 {
+            let _ = sut.try_rotate();
+            while sut.try_move(MovementDirection::Right).is_ok() {}
+            let _ = sut.try_move(MovementDirection::Top);
+            sut.lock_current_piece();
+            clears.extend(sut.drain_events().into_iter().filter_map(|event| match event {
+                GameEvent::LinesCleared { kind, .. } => Some(kind),
+                _ => None
+            }));
+        }
+
+        assert_eq!(clears.iter().map(|clear| (clear.lines, clear.back_to_back)).collect::<Vec<_>>(), vec![(4, false), (4, true)]);
+    }
+
+
+    #[test]
+    fn pentomino_t_never_makes_a_t_spin() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let mut sut = GameBoard::new(rng.clone(), 1);
+        let t = PieceFactory::pentominoes().get_definition(PieceType::T).unwrap().clone();
+        sut.set_piece_provider(PieceProvider::with_piece_set(PieceSet::new(vec![t]).unwrap(), rng));
+        let x = sut.get_current_piece().get_squares()[0].get_position().x;
+        sut.place_square(Square::new(Vector2::new(x - 1, 0), Color::Gray));
+
+        let _ = sut.try_move(MovementDirection::Top);
+        // Turned on its side, two squares of the piece and the one below are on three corners around its center
+        assert!(sut.try_rotate().is_ok());
+        sut.lock_current_piece();
+
+        assert_eq!(sut.get_last_clear(), None);
+    }
+
+
+    #[test]
+    fn nes_single_of_the_first_level_is_worth_40_points() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let mut sut = GameBoard::new(rng, 1);
+        sut.set_scoring(ScoringKind::Nes.create());
+        sut.set_piece_provider(PieceProvider::from_sequence(vec![PieceType::I; 2]));
+        let columns: Vec<isize> = sut.get_current_piece().get_squares().iter().map(|square| square.get_position().x).collect();
+        for x in (0..BOARD_WIDTH as isize).filter(|x| !columns.contains(x)) {
+            sut.place_square(Square::new(Vector2::new(x, 0), Color::Gray));
+        }
+
+        let _ = sut.try_move(MovementDirection::Top);
+        sut.lock_current_piece();
+
+        assert_eq!(sut.get_lines_cleared(), 1);
+        assert_eq!(sut.get_score(), 40);
+    }
+}
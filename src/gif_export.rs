@@ -0,0 +1,175 @@
+use std::{fmt::Display, fs::File, io::BufWriter, path::{Path, PathBuf}};
+
+use gif::{Encoder, Frame, Repeat};
+
+use crate::{replay::Replay, scene::build_scene, snapshot::{Snapshot, SceneBounds}, game::STEPS_PER_SECOND};
+
+// Speed of the color quantization, from 1 (best colors) to 30 (fastest)
+const QUANTIZATION_SPEED: i32 = 10;
+
+#[derive(Debug)]
+pub struct GifError(String);
+
+impl Display for GifError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct GifOptions {
+    pub output: PathBuf,
+    // One picture every this many game frames
+    pub every: u32,
+    // Playback speed, 2.0 playing twice as fast as the game was played
+    pub speed: f32,
+    pub from_frame: u32,
+    pub to_frame: Option<u32>,
+    pub cell_pixels: u32
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        GifOptions {
+            output: PathBuf::from("replay.gif"),
+            every: 12,
+            speed: 1.0,
+            from_frame: 0,
+            to_frame: None,
+            cell_pixels: 16
+        }
+    }
+}
+
+impl GifOptions {
+
+    // GIF delays are counted in hundredths of a second
+    fn get_picture_time(&self) -> f64 {
+        self.every as f64 * 100.0 / STEPS_PER_SECOND as f64 / self.speed as f64
+    }
+
+
+    // Pictures closer than a hundredth of a second cannot be timed in a GIF
+    pub fn has_valid_delay(&self) -> bool {
+        self.get_picture_time() >= 1.0
+    }
+
+
+    // Delays are rounded from the start of the GIF, so rounding errors do not add up
+    fn get_delay(&self, picture: u32) -> u16 {
+        let shown_at = |picture: u32| (picture as f64 * self.get_picture_time()).round() as u64;
+        (shown_at(picture + 1) - shown_at(picture)) as u16
+    }
+
+
+    fn is_pictured(&self, frame: u32) -> bool {
+        frame >= self.from_frame && (frame - self.from_frame).is_multiple_of(self.every)
+    }
+}
+
+
+// Plays the replay twice, once to size the pictures after everything shown then once to draw them
+pub fn export_gif(replay: &Replay, options: &GifOptions) -> Result<u32, GifError> {
+    let mut bounds: Option<SceneBounds> = None;
+    replay.play_with(options.to_frame, |game| {
+        if options.is_pictured(game.get_frame()) {
+            let frame_bounds = SceneBounds::of_blocks(&build_scene(game.get_board()));
+            bounds = Some(bounds.map_or(frame_bounds, |bounds| bounds.union(&frame_bounds)));
+        }
+    });
+    let bounds = bounds.ok_or(GifError(format!("The replay ends before frame {}", options.from_frame)))?;
+
+    let error = |error: &dyn Display| GifError(format!("Cannot write {} : {}", options.output.display(), error));
+    let (width, height) = Snapshot::get_size(bounds, options.cell_pixels);
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(GifError(format!("Pictures of {}x{} pixels are too large for a GIF", width, height)));
+    }
+    let file = File::create(&options.output).map_err(|e| error(&e))?;
+    let mut encoder = Encoder::new(BufWriter::new(file), width as u16, height as u16, &[]).map_err(|e| error(&e))?;
+    encoder.set_repeat(Repeat::Infinite).map_err(|e| error(&e))?;
+
+    let mut pictures = 0;
+    let mut result = Ok(());
+    replay.play_with(options.to_frame, |game| {
+        if result.is_err() || !options.is_pictured(game.get_frame()) {
+            return;
+        }
+        let snapshot = Snapshot::within(game.get_board(), bounds, options.cell_pixels);
+        let mut frame = Frame::from_rgb_speed(width as u16, height as u16, snapshot.get_pixels(), QUANTIZATION_SPEED);
+        frame.delay = options.get_delay(pictures);
+        result = encoder.write_frame(&frame).map_err(|e| error(&e));
+        pictures += 1;
+    });
+    result.map(|_| pictures)
+}
+
+
+pub fn export_gif_file(replay: &Path, options: &GifOptions) -> Result<u32, GifError> {
+    let replay = Replay::load(replay).map_err(|error| GifError(error.to_string()))?;
+    export_gif(&replay, options)
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+
+    use crate::{game_mode::{ModeKind, ModeOptions}, gameboard::Action, replay::Replay};
+
+    use super::{export_gif, GifOptions};
+
+    fn replay() -> Replay {
+        let mut replay = Replay::new(5, ModeKind::Marathon, ModeOptions::default());
+        replay.record(30, Action::Hold);
+        replay.set_frames(240);
+        replay
+    }
+
+    #[test]
+    fn delay_follows_the_picture_rate_and_speed() {
+        let options = GifOptions { every: 12, speed: 2.0, ..GifOptions::default() };
+
+        assert_eq!(GifOptions::default().get_delay(0), 10);
+        assert_eq!(options.get_delay(7), 5);
+    }
+
+
+    #[test]
+    fn rounding_errors_of_the_delays_do_not_add_up() {
+        // A picture every 2.5 hundredths of a second
+        let options = GifOptions { every: 3, ..GifOptions::default() };
+
+        let delays = (0..10).map(|picture| options.get_delay(picture)).collect::<Vec<_>>();
+
+        assert_eq!(delays.iter().sum::<u16>(), 25);
+        assert!(delays.iter().all(|delay| (2..=3).contains(delay)));
+        assert!(!GifOptions { every: 1, ..GifOptions::default() }.has_valid_delay());
+    }
+
+
+    #[test]
+    fn only_the_chosen_range_is_exported() {
+        let output = std::env::temp_dir().join("tetrust_export_test.gif");
+        let options = GifOptions { output: output.clone(), every: 20, from_frame: 40, to_frame: Some(200), ..GifOptions::default() };
+
+        let pictures = export_gif(&replay(), &options).unwrap();
+
+        assert_eq!(pictures, 9);
+        let mut decoder = gif::DecodeOptions::new().read_info(File::open(&output).unwrap()).unwrap();
+        let mut decoded = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            decoded += 1;
+        }
+        assert_eq!(decoded, pictures);
+        let _ = std::fs::remove_file(output);
+    }
+
+
+    #[test]
+    fn starting_after_the_end_is_an_error() {
+        let options = GifOptions { from_frame: 1000, ..GifOptions::default() };
+
+        assert!(export_gif(&replay(), &options).is_err());
+    }
+}
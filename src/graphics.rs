@@ -1,130 +1,464 @@
-use colored::{Colorize, ColoredString};
+use std::io::Write;
 
-use crate::{gameboard::{GameBoard, BOARD_HEIGHT, BOARD_WIDTH}, piece::{Square, Color, Piece}};
+use colored::Colorize;
+use console::Term;
+use nalgebra::Vector2;
 
-const SQUARE_LEFT_STR: char = '\u{27E6}';
-const SQUARE_RIGHT_STR: char = '\u{27E7}';
+use crate::{gameboard::GameBoard, piece::{Color, Piece}, game_stats::GameStats, cell_grid::{CellGrid, Cell}, theme::{Theme, ColorDepth, Palette}, piece_factory::PieceType, layout::{Layout, Panel, Placement}, animation::Animations, game_event::GameEvent, menu};
 
 pub trait Visualizer {
-    fn display(board: &GameBoard);
+    fn display(&mut self, board: &GameBoard, stats: &GameStats);
+
+    // Whether to display again even though the game did not change, like after a resize
+    fn needs_redraw(&self) -> bool {
+        false
+    }
+
+    // Events and clock of the game, for renderers animating what happens
+    fn record(&mut self, _event: &GameEvent) {}
+
+    fn advance_time(&mut self, _time_delta: f32) {}
+
+    // Board hidden while the game is paused, shown again by the next display
+    fn hide(&mut self) {}
+
+    // Finished game shown with a panel, like a menu, in the terminal.
+    // Renderers drawing elsewhere leave the board where it is
+    fn display_beside(&mut self, _board: &GameBoard, panel: &Panel) {
+        menu::show(panel);
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RendererKind {
+    Ascii,
+    // Only available when built with the 3d feature
+    Kiss3d
 }
 
-pub struct AsciiVisualizer;
+impl RendererKind {
 
-impl AsciiVisualizer {
+    pub fn from_name(name: &str) -> Option<RendererKind> {
+        match name {
+            "ascii" => Some(RendererKind::Ascii),
+            "3d" => Some(RendererKind::Kiss3d),
+            _ => None
+        }
+    }
 
-    pub fn colored_ascii_square_from(color: Color) -> ColoredString {
-        
-        let string = format!("{}{}", SQUARE_LEFT_STR, SQUARE_RIGHT_STR);
-        match color {
-            Color::Cyan => string.bright_blue().on_cyan(),
-            Color::DarkBlue => string.blue().on_bright_blue(),
-            Color::Orange => string.truecolor(255, 128, 0).on_bright_red(),
-            Color::Yellow => string.yellow().on_bright_yellow(),
-            Color::Green => string.green().on_bright_green(),
-            Color::Purple => string.purple().on_bright_purple(),
-            Color::Red => string.red().on_bright_red(),
+
+    pub fn is_available(&self) -> bool {
+        match self {
+            RendererKind::Ascii => true,
+            RendererKind::Kiss3d => cfg!(feature = "3d")
         }
     }
 
 
-    pub fn colored_ascii_of_piece(piece: Option<Piece>, cursor_position: usize) -> String {
-        if let None = piece {
-            return "None".white().to_string();
+    pub fn create(&self, options: &DisplayOptions) -> Box<dyn Visualizer> {
+        match self {
+            #[cfg(feature = "3d")]
+            RendererKind::Kiss3d => Box::new(crate::graphics_3d::Kiss3dVisualizer::new()),
+            _ => Box::new(AsciiVisualizer::new(options))
         }
+    }
+}
 
-        let piece = piece.unwrap();
-        let color = piece.get_squares()[0].get_color();
-        let array = piece.to_array();
-        let mut building_string = String::new();
-        for line in array.iter().rev() {
-            for value in line {
-                let value_string = match value {
-                    true => AsciiVisualizer::colored_ascii_square_from(color),
-                    false => "  ".white()
-                };
-                building_string.push_str(&value_string.to_string());
+
+// How the terminal renderer draws the game
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct DisplayOptions {
+    pub theme: Theme,
+    // Replaces the square colors of the theme
+    pub palette: Option<Palette>,
+    // Squares show the letter of their piece, so pieces are not told apart by color alone
+    pub piece_letters: bool
+}
+
+
+// Terminal screen split in cells, each holding one character with its own style
+#[derive(Clone, PartialEq, Debug)]
+pub struct Frame {
+    lines: Vec<Vec<String>>,
+    // Column each cell of a line starts at, wide characters taking two, then the width of the line
+    columns: Vec<Vec<usize>>
+}
+
+impl Frame {
+
+    // Styles are carried from cell to cell until reset, "\n" ends a line and "\r" is ignored
+    pub fn parse(text: &str) -> Frame {
+        let mut lines = vec![];
+        let mut columns = vec![];
+        for text_line in text.split('\n') {
+            let mut line = vec![];
+            let mut line_columns = vec![0];
+            let mut style = String::new();
+            let mut characters = text_line.chars().filter(|character| *character != '\r');
+            while let Some(character) = characters.next() {
+                if character != '\x1B' {
+                    match style.is_empty() {
+                        true => line.push(character.to_string()),
+                        false => line.push(format!("{}{}\x1B[0m", style, character))
+                    }
+                    let width = console::measure_text_width(&character.to_string());
+                    line_columns.push(line_columns.last().unwrap() + width);
+                    continue;
+                }
+
+                let mut sequence = String::from(character);
+                for next in characters.by_ref() {
+                    sequence.push(next);
+                    if next.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+                match sequence.as_str() {
+                    "\x1B[0m" | "\x1B[m" => style.clear(),
+                    _ => style.push_str(&sequence)
+                }
+            }
+            lines.push(line);
+            columns.push(line_columns);
+        }
+        Frame {
+            lines,
+            columns
+        }
+    }
+
+
+    pub fn get_height(&self) -> usize {
+        self.lines.len()
+    }
+
+
+    // Escape sequence turning the previous frame into this one, a run of changed cells
+    // on a line needing a single cursor move
+    pub fn diff(&self, previous: &Frame) -> String {
+        let mut output = String::new();
+        let (empty, empty_columns) = (vec![], vec![0]);
+        for (row, (line, columns)) in self.lines.iter().zip(self.columns.iter()).enumerate() {
+            let previous_line = previous.lines.get(row).unwrap_or(&empty);
+            let previous_columns = previous.columns.get(row).unwrap_or(&empty_columns);
+            let mut cursor_placed = false;
+            for (cell, column) in line.iter().zip(columns.iter()) {
+                // A cell is kept when the same one starts at the same column
+                let previous_cell = previous_columns.binary_search(column).ok().and_then(|index| previous_line.get(index));
+                if previous_cell == Some(cell) {
+                    cursor_placed = false;
+                    continue;
+                }
+                if !cursor_placed {
+                    output.push_str(&format!("\x1B[{};{}H", row + 1, column + 1));
+                    cursor_placed = true;
+                }
+                output.push_str(cell);
+            }
+            let (width, previous_width) = (columns[line.len()], previous_columns[previous_line.len()]);
+            if previous_width > width {
+                output.push_str(&format!("\x1B[{};{}H\x1B[K", row + 1, width + 1));
             }
-            let align = std::iter::repeat(" ").take(cursor_position).collect::<String>();
-            building_string.push_str("\n\r");
-            building_string.push_str(&align);
         }
-        building_string
+        for row in self.lines.len()..previous.lines.len() {
+            output.push_str(&format!("\x1B[{};1H\x1B[K", row + 1));
+        }
+        output
     }
 }
 
 
-impl Visualizer for AsciiVisualizer {
+pub struct AsciiVisualizer {
+    // What is on the screen, None before the first display
+    previous_frame: Option<Frame>,
+    theme: Theme,
+    depth: ColorDepth,
+    piece_letters: bool,
+    // Rows and columns the frame was laid out for
+    terminal_size: (usize, usize),
+    animations: Animations
+}
+
+impl AsciiVisualizer {
+
+    pub fn new(options: &DisplayOptions) -> Self {
+        AsciiVisualizer {
+            previous_frame: None,
+            theme: options.palette.map_or(options.theme.clone(), |palette| palette.apply(&options.theme)),
+            depth: ColorDepth::detect(),
+            piece_letters: options.piece_letters,
+            terminal_size: (0, 0),
+            animations: Animations::new()
+        }
+    }
 
-    fn display(board: &GameBoard){
-        print!("\x1B[2J\x1B[1;1H");
-        let mut square_board = board.get_square_board().clone();
-        for square in board.get_current_piece().get_squares() {
-            let position = square.get_position();
-            square_board[position.x as usize][position.y as usize] = Some(square.clone());
+
+    pub fn colored_ascii_square_from(&self, color: Color, piece_type: Option<PieceType>) -> String {
+        match piece_type.filter(|_| self.piece_letters) {
+            Some(piece_type) => self.theme.paint(color, self.depth, &format!("{} ", piece_type.0)),
+            None => self.theme.paint_square(color, self.depth)
         }
+    }
+
+
+    // Rows of a piece preview, the top one first
+    pub fn colored_ascii_of_piece(&self, piece: Option<Piece>) -> Vec<String> {
+        let piece = match piece {
+            Some(piece) => piece,
+            None => return vec!["None".white().to_string()]
+        };
+        let color = piece.get_squares()[0].get_color();
+        piece.to_array().iter().rev()
+            .map(|line| line.iter().map(|value| match value {
+                true => self.colored_ascii_square_from(color, Some(piece.get_piece_type())),
+                false => String::from("  ")
+            }).collect())
+            .collect()
+    }
 
-        let mut building_text = String::new();
-        let header = "\n\r";
-        let left_shift = "                ";
-        let left_border = "\u{23B9}".bold();
-        let right_border = "\u{23B8}".bold();
-        let bottom_border = "\u{203E}".bold();
-
-        building_text.push_str(header);
-
-        let score_string = format!("  Your score : {}\n\r", board.get_score()).bold();
-        let level_string = format!("  Level : {}\n\r", board.get_level()).bold();
-        let lines_cleared_string = format!("  Lines cleared : {}\n\r", board.get_lines_cleared()).bold();
-        building_text.push_str(&score_string);
-        building_text.push_str(&level_string);
-        building_text.push_str(&lines_cleared_string);
-
-        building_text.push_str("\n\r");
-
-        let next_string = "The next piece is :  ";
-        let next_piece: Piece = board.get_next_piece().clone();
-        let cursor_position = next_string.len() + left_shift.len();
-        let next_piece_string = AsciiVisualizer::colored_ascii_of_piece(Some(next_piece), cursor_position);
-        building_text.push_str(left_shift);
-        building_text.push_str(next_string);
-        building_text.push_str(&next_piece_string);
-        building_text.push_str("\n\r");
-
-        for i in (0..BOARD_HEIGHT).rev() {
-            building_text.push_str(left_shift);
-            building_text.push_str(&left_border.to_string());
-
-            for j in 0..BOARD_WIDTH {
-                let value = square_board[j][i];
-                let string = match value {
-                    Some(square) => AsciiVisualizer::colored_ascii_square_from(square.get_color()).bold(),
-                    None => String::from("\u{00B7}\u{00B7}").white()
-                };
-                
-                building_text.push_str(&string.to_string());
+
+    fn render_well(&self, board: &GameBoard) -> Vec<String> {
+        let grid = CellGrid::of_board(board);
+        let dimensions = board.get_dimensions();
+        let left_border = self.theme.get_left_border().bold().to_string();
+        let right_border = self.theme.get_right_border().bold().to_string();
+        let bottom_border = self.theme.get_bottom_border().bold().to_string();
+
+        // Cleared rows and the piece just locked flash over whatever is there now
+        let flash = self.theme.get_square_glyphs().black().on_bright_white().to_string();
+        let is_flashed = |x: usize, y: usize| self.animations.get_cleared_rows().contains(&y) ||
+            self.animations.get_locked_squares().contains(&Vector2::new(x as isize, y as isize));
+
+        // Big boards draw every logical cell cell_size times in both directions
+        let mut lines = vec![];
+        for (row_index, row) in grid.get_rows().iter().enumerate() {
+            let y = dimensions.height - 1 - row_index;
+            // The ghost is left out of the terminal
+            let cells = row.iter().enumerate().map(|(x, cell)| match cell {
+                _ if is_flashed(x, y) => flash.clone(),
+                Cell::Block(color, piece_type) => self.colored_ascii_square_from(*color, *piece_type),
+                Cell::Empty | Cell::Ghost(_) => self.theme.get_empty_glyphs().white().to_string()
+            }.repeat(dimensions.cell_size)).collect::<String>();
+            for _ in 0..dimensions.cell_size {
+                lines.push(format!("{}{}{}", left_border, cells, right_border));
             }
-            building_text.push_str(&right_border.to_string());
-            building_text.push_str("\n\r")
         }
+        lines.push(format!(" {}", bottom_border.repeat(2 * dimensions.width * dimensions.cell_size)));
+        lines
+    }
+
+
+    // Hold on the left of the well above the statistics, next queue on its right and scores above,
+    // centered in a terminal of the given rows and columns
+    fn render(&self, board: &GameBoard, stats: &GameStats, terminal: (usize, usize)) -> String {
+        let (rows, columns) = terminal;
+        let mut layout = Layout::new(Panel::new(self.render_well(board)));
+        layout.place(Placement::Above, self.render_scores(board));
+
+        let hold = Panel::new(self.colored_ascii_of_piece(board.get_held_piece().clone()));
+        layout.place(Placement::Left, hold.with_title("Hold".bold().to_string()));
+        let statistics = stats.get_summary().into_iter()
+            .map(|(label, value)| format!("{:<11}{}", label, value))
+            .chain(stats.get_finesse_feedback().map(|feedback| feedback.yellow().to_string()));
+        layout.place(Placement::Left, Panel::new(statistics.collect()));
+
+        let queue = board.get_next_pieces().iter()
+            .map(|piece| Panel::new(self.colored_ascii_of_piece(Some(piece.clone()))))
+            .reduce(Panel::above)
+            .unwrap_or(Panel::new(vec!["None".white().to_string()]));
+        layout.place(Placement::Right, queue.with_title("Next".bold().to_string()));
+
+        layout.compose(columns).center_in(rows, columns)
+    }
+
+
+    // The panel takes the place of the hold, queue and statistics
+    fn render_beside(&self, board: &GameBoard, panel: &Panel, terminal: (usize, usize)) -> String {
+        let (rows, columns) = terminal;
+        let mut layout = Layout::new(Panel::new(self.render_well(board)));
+        layout.place(Placement::Above, self.render_scores(board));
+        layout.place(Placement::Right, panel.clone());
+        layout.compose(columns).center_in(rows, columns)
+    }
+
+
+    fn render_scores(&self, board: &GameBoard) -> Panel {
+        Panel::new(vec![
+            String::new(),
+            format!("  Your score : {}", board.get_score()).bold().to_string(),
+            format!("  Level : {}", board.get_level()).bold().to_string(),
+            format!("  Lines cleared : {}", board.get_lines_cleared()).bold().to_string(),
+            // Always there so the well does not move when a banner shows
+            self.animations.get_banner().map_or(String::new(), |banner| format!("  {}", banner).yellow().bold().to_string())
+        ])
+    }
 
-        building_text.push_str(left_shift);
-        building_text.push_str(" ");
-        for _ in 0..BOARD_WIDTH {
-            building_text.push_str(&bottom_border.to_string());
-            building_text.push_str(&bottom_border.to_string());
+
+    fn get_terminal_size() -> (usize, usize) {
+        let (rows, columns) = Term::stdout().size();
+        (rows as usize, columns as usize)
+    }
+}
+
+
+impl Visualizer for AsciiVisualizer {
+
+    fn display(&mut self, board: &GameBoard, stats: &GameStats){
+        // A resized terminal is cleared and the layout started over
+        let terminal_size = AsciiVisualizer::get_terminal_size();
+        if terminal_size != self.terminal_size {
+            self.terminal_size = terminal_size;
+            self.previous_frame = None;
         }
-        building_text.push_str("\n\r");
 
-        let held_string = "The held piece is :";
-        let held_piece = board.get_held_piece().clone();
-        let cursor_position = held_string.len() + left_shift.len();
-        let held_piece_string = AsciiVisualizer::colored_ascii_of_piece(held_piece, cursor_position);
-        building_text.push_str(left_shift);
-        building_text.push_str(held_string);
-        building_text.push_str(&held_piece_string);
-        building_text.push_str("\n\r");
+        let frame = Frame::parse(&self.render(board, stats, terminal_size));
+        let output = match &self.previous_frame {
+            Some(previous) => frame.diff(previous),
+            None => format!("\x1B[2J{}", frame.diff(&Frame { lines: vec![], columns: vec![] }))
+        };
+
+        // The cursor is left under the frame for whatever is printed next
+        print!("{}\x1B[{};1H", output, frame.get_height() + 1);
+        let _ = std::io::stdout().flush();
+        self.previous_frame = Some(frame);
+        self.animations.set_drawn();
+    }
+
+
+    fn needs_redraw(&self) -> bool {
+        self.animations.has_changed() || AsciiVisualizer::get_terminal_size() != self.terminal_size
+    }
+
+
+    fn record(&mut self, event: &GameEvent) {
+        self.animations.record(event);
+    }
+
+
+    fn advance_time(&mut self, time_delta: f32) {
+        self.animations.advance_time(time_delta);
+    }
+
+
+    // Whatever covered the board, the next frame is drawn whole
+    fn hide(&mut self) {
+        self.previous_frame = None;
+    }
+
+
+    fn display_beside(&mut self, board: &GameBoard, panel: &Panel) {
+        menu::show_text(&self.render_beside(board, panel, AsciiVisualizer::get_terminal_size()));
+        self.previous_frame = None;
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use console::measure_text_width;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{gameboard::GameBoard, game_stats::GameStats, game_event::GameEvent, layout::Panel, piece::Color, theme::{Theme, ColorDepth}};
+
+    use super::{Frame, AsciiVisualizer, DisplayOptions, Visualizer};
+
+    fn render(terminal: (usize, usize)) -> Vec<String> {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let board = GameBoard::new(rng, 1);
+        let stats = GameStats::new(board.get_dimensions());
+        let sut = AsciiVisualizer::new(&DisplayOptions::default());
+        sut.render(&board, &stats, terminal).split("\n\r").map(String::from).collect()
+    }
+
+    #[test]
+    fn styles_are_kept_on_every_cell_until_reset() {
+        let sut = Frame::parse("\x1B[1mab\x1B[0mc\n\rd");
 
-        println!("{}", building_text);
+        assert_eq!(sut.lines, vec![
+            vec![String::from("\x1B[1ma\x1B[0m"), String::from("\x1B[1mb\x1B[0m"), String::from("c")],
+            vec![String::from("d")]
+        ]);
     }
-}
\ No newline at end of file
+
+
+    #[test]
+    fn only_changed_cells_are_redrawn() {
+        let previous = Frame::parse("abcd\nefgh");
+        let sut = Frame::parse("abXY\nefgZ");
+
+        assert_eq!(sut.diff(&previous), "\x1B[1;3HXY\x1B[2;4HZ");
+        assert_eq!(sut.diff(&sut), "");
+    }
+
+
+    #[test]
+    fn wide_glyphs_of_a_theme_move_the_cursor_by_two_columns() {
+        let theme = Theme::parse(&include_str!("../themes/ascii.txt").replace("square: []", "square: 口")).unwrap();
+        let square = theme.paint_square(Color::Cyan, ColorDepth::TrueColor);
+        let previous = Frame::parse(&format!("|{}ab\n|  a", square));
+        let sut = Frame::parse(&format!("|{}aX\n|{}a", square, square));
+
+        assert_eq!(sut.diff(&previous), format!("\x1B[1;5HX\x1B[2;2H{}", square));
+        assert_eq!(previous.diff(&sut), "\x1B[1;5Hb\x1B[2;2H  ");
+    }
+
+
+    #[test]
+    fn shorter_lines_and_frames_are_cleared() {
+        let previous = Frame::parse("abcd\nefgh\nijkl");
+        let sut = Frame::parse("ab\nefgh");
+
+        assert_eq!(sut.diff(&previous), "\x1B[1;3H\x1B[K\x1B[3;1H\x1B[K");
+    }
+
+
+    #[test]
+    fn statistics_are_beside_the_well_when_they_fit() {
+        let wide = render((40, 120));
+        let narrow = render((40, 30));
+
+        let well_line = |lines: &Vec<String>| lines.iter().find(|line| line.contains("Pieces")).unwrap().contains('\u{23B9}');
+        assert!(well_line(&wide));
+        assert!(!well_line(&narrow));
+    }
+
+
+    #[test]
+    fn panels_are_drawn_beside_the_final_board() {
+        let board = GameBoard::new(ChaCha8Rng::seed_from_u64(1), 1);
+        let sut = AsciiVisualizer::new(&DisplayOptions::default());
+
+        let text = sut.render_beside(&board, &Panel::new(vec![String::from("Game over")]), (40, 120));
+
+        let line = text.split("\n\r").find(|line| line.contains("Game over")).unwrap();
+        assert!(line.contains('\u{23B9}'));
+    }
+
+
+    #[test]
+    fn layout_is_centered_in_the_terminal() {
+        let small = render((10, 60));
+        let large = render((80, 200));
+
+        let indent = |line: &String| line.len() - line.trim_start().len();
+        let well_line = |lines: &Vec<String>| lines.iter().find(|line| line.contains('\u{23B9}')).unwrap().clone();
+        assert!(indent(&well_line(&large)) > indent(&well_line(&small)));
+        assert!(large.iter().take_while(|line| line.is_empty()).count() > 0);
+        assert!(small.iter().all(|line| measure_text_width(line) <= 60));
+    }
+
+
+    #[test]
+    fn banners_of_recorded_events_are_shown_above_the_well() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let board = GameBoard::new(rng, 1);
+        let stats = GameStats::new(board.get_dimensions());
+        let mut sut = AsciiVisualizer::new(&DisplayOptions::default());
+
+        sut.record(&GameEvent::LevelUp(2));
+
+        assert!(sut.needs_redraw());
+        assert!(sut.render(&board, &stats, (40, 120)).contains("LEVEL UP"));
+    }
+}
@@ -0,0 +1,130 @@
+use kiss3d::{window::Window, light::Light, camera::ArcBall, scene::SceneNode};
+use nalgebra::{Point3, Translation3, Vector3};
+
+use crate::{gameboard::GameBoard, graphics::Visualizer, game_stats::GameStats, scene::build_scene, layout::Panel, menu};
+
+const CAMERA_DISTANCE: f32 = 40.0;
+const BACKGROUND_COLOR: [f32; 3] = [0.05, 0.05, 0.1];
+const WINDOW_TITLE: &str = "Tetrust - keys are read in the terminal";
+
+struct WindowState {
+    window: Window,
+    camera: ArcBall,
+    scene: Option<SceneNode>
+}
+
+// Only draws the game, the keys staying read in the terminal the game was started from
+pub struct Kiss3dVisualizer {
+    // Opened on the first display, sized after the board
+    state: Option<WindowState>,
+    // The terminal tells where the game went, until a menu is drawn over it
+    notice_shown: bool
+}
+
+impl Kiss3dVisualizer {
+
+    pub fn new() -> Self {
+        Kiss3dVisualizer {
+            state: None,
+            notice_shown: false
+        }
+    }
+
+
+    // The window is given so tests can draw in a hidden one
+    fn open(board: &GameBoard, mut window: Window) -> WindowState {
+        let dimensions = board.get_dimensions();
+        let center = Point3::new(
+            (dimensions.width * dimensions.cell_size) as f32 / 2.0,
+            (dimensions.height * dimensions.cell_size) as f32 / 2.0,
+            0.0
+        );
+        window.set_light(Light::StickToCamera);
+        window.set_background_color(BACKGROUND_COLOR[0], BACKGROUND_COLOR[1], BACKGROUND_COLOR[2]);
+        WindowState {
+            window,
+            camera: ArcBall::new(center + Vector3::new(0.0, 0.0, CAMERA_DISTANCE), center),
+            scene: None
+        }
+    }
+
+
+    // The scene is rebuilt from scratch on every update
+    fn draw(state: &mut WindowState, board: &GameBoard) {
+        if let Some(mut scene) = state.scene.take() {
+            state.window.remove_node(&mut scene);
+        }
+        let mut scene = state.window.add_group();
+        for block in build_scene(board) {
+            let mut node = scene.add_cube(block.size, block.size, block.size);
+            node.set_color(block.color[0], block.color[1], block.color[2]);
+            node.set_local_translation(Translation3::from(block.position));
+        }
+        state.scene = Some(scene);
+
+        state.window.render_with_camera(&mut state.camera);
+    }
+}
+
+impl Visualizer for Kiss3dVisualizer {
+
+    fn display(&mut self, board: &GameBoard, _stats: &GameStats) {
+        if !self.notice_shown {
+            menu::show(&Panel::new(vec![
+                String::from("The game is drawn in the 3D window"),
+                String::from("Keys are read here, keep this terminal focused")
+            ]));
+            self.notice_shown = true;
+        }
+        let state = self.state.get_or_insert_with(|| Kiss3dVisualizer::open(board, Window::new(WINDOW_TITLE)));
+        Kiss3dVisualizer::draw(state, board);
+    }
+
+
+    // Rendered on every frame, a window left without it is seen as not responding by the system
+    fn advance_time(&mut self, _time_delta: f32) {
+        if let Some(state) = self.state.as_mut() {
+            state.window.render_with_camera(&mut state.camera);
+        }
+    }
+
+
+    fn hide(&mut self) {
+        self.notice_shown = false;
+        if let Some(state) = self.state.as_mut() {
+            if let Some(mut scene) = state.scene.take() {
+                state.window.remove_node(&mut scene);
+            }
+            state.window.render_with_camera(&mut state.camera);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use kiss3d::window::Window;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{gameboard::GameBoard, game_stats::GameStats, graphics::Visualizer};
+
+    use super::Kiss3dVisualizer;
+
+    #[test]
+    #[ignore = "needs an OpenGL context"]
+    fn board_stays_rendered_between_displays() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let board = GameBoard::new(rng, 1);
+        let state = Kiss3dVisualizer::open(&board, Window::new_hidden("Tetrust"));
+        let mut sut = Kiss3dVisualizer { state: Some(state), notice_shown: true };
+
+        sut.display(&board, &GameStats::new(board.get_dimensions()));
+        sut.advance_time(0.1);
+        sut.advance_time(0.1);
+
+        let image = sut.state.unwrap().window.snap_image();
+        let background = image.get_pixel(0, 0);
+        assert!(image.pixels().any(|pixel| pixel != background));
+    }
+}
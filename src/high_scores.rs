@@ -0,0 +1,164 @@
+use std::{fmt::Display, fs, path::{Path, PathBuf}};
+
+use crate::game_mode::ModeKind;
+
+// Entries kept for every mode
+const MAX_ENTRIES: usize = 10;
+
+#[derive(Debug)]
+pub struct HighScoreError(String);
+
+impl Display for HighScoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct HighScore {
+    pub score: u32,
+    pub lines: u32,
+    pub level: u32
+}
+
+/*
+ * High scores are kept in a text file, one entry per line, "#" starting a comment :
+ *
+ *   marathon: 12000 40 6      (mode, then score, lines and level)
+ */
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct HighScores {
+    // Best scores first, all modes mixed
+    entries: Vec<(ModeKind, HighScore)>
+}
+
+impl HighScores {
+
+    // In the data directory of the user, e.g. ~/.local/share/tetrust on Linux
+    pub fn get_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|directory| directory.join("tetrust").join("high_scores.txt"))
+    }
+
+
+    // No file yet is an empty table
+    pub fn load(path: &Path) -> Result<HighScores, HighScoreError> {
+        if !path.exists() {
+            return Ok(HighScores::default());
+        }
+        let text = fs::read_to_string(path)
+            .map_err(|error| HighScoreError(format!("Cannot read {} : {}", path.display(), error)))?;
+        HighScores::parse(&text)
+    }
+
+
+    pub fn save(&self, path: &Path) -> Result<(), HighScoreError> {
+        let error = |error: std::io::Error| HighScoreError(format!("Cannot write {} : {}", path.display(), error));
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(error)?;
+        }
+        fs::write(path, self.to_text()).map_err(error)
+    }
+
+
+    pub fn parse(text: &str) -> Result<HighScores, HighScoreError> {
+        let mut high_scores = HighScores::default();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let entry = line.split_once(':').and_then(|(mode, values)| {
+                let values = values.split_whitespace().map(|value| value.parse::<u32>().ok()).collect::<Option<Vec<_>>>()?;
+                match values[..] {
+                    [score, lines, level] => Some((ModeKind::from_name(mode.trim())?, HighScore { score, lines, level })),
+                    _ => None
+                }
+            });
+            let (mode, high_score) = entry.ok_or(HighScoreError(format!("Expected \"<mode>: <score> <lines> <level>\", found : {}", line)))?;
+            high_scores.add(mode, high_score);
+        }
+        Ok(high_scores)
+    }
+
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# mode: score lines level\n");
+        for (mode, high_score) in self.entries.iter() {
+            text.push_str(&format!("{}: {} {} {}\n", mode.get_name(), high_score.score, high_score.lines, high_score.level));
+        }
+        text
+    }
+
+
+    pub fn get_entries(&self, mode: ModeKind) -> Vec<HighScore> {
+        self.entries.iter().filter(|(kind, _)| *kind == mode).map(|(_, high_score)| *high_score).collect()
+    }
+
+
+    // Rank of the new entry from 1, None when it does not make the table
+    pub fn add(&mut self, mode: ModeKind, high_score: HighScore) -> Option<usize> {
+        if high_score.score == 0 {
+            return None;
+        }
+        // Older entries stay ahead of new ones with the same score
+        let rank = self.get_entries(mode).iter().filter(|entry| entry.score >= high_score.score).count();
+        if rank >= MAX_ENTRIES {
+            return None;
+        }
+
+        let index = self.entries.iter().position(|(_, entry)| entry.score < high_score.score).unwrap_or(self.entries.len());
+        self.entries.insert(index, (mode, high_score));
+        if self.get_entries(mode).len() > MAX_ENTRIES {
+            let last = self.entries.iter().rposition(|(kind, _)| *kind == mode).unwrap();
+            self.entries.remove(last);
+        }
+        Some(rank + 1)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::game_mode::ModeKind;
+
+    use super::{HighScores, HighScore, MAX_ENTRIES};
+
+    fn entry(score: u32) -> HighScore {
+        HighScore { score, lines: score / 100, level: 1 }
+    }
+
+    #[test]
+    fn entries_are_ranked_by_mode() {
+        let mut sut = HighScores::default();
+
+        assert_eq!(sut.add(ModeKind::Marathon, entry(500)), Some(1));
+        assert_eq!(sut.add(ModeKind::Zen, entry(900)), Some(1));
+        assert_eq!(sut.add(ModeKind::Marathon, entry(800)), Some(1));
+        assert_eq!(sut.add(ModeKind::Marathon, entry(500)), Some(3));
+        assert_eq!(sut.add(ModeKind::Marathon, entry(0)), None);
+
+        assert_eq!(sut.get_entries(ModeKind::Marathon), vec![entry(800), entry(500), entry(500)]);
+    }
+
+
+    #[test]
+    fn only_the_best_entries_are_kept() {
+        let mut sut = HighScores::default();
+        for score in 1..=MAX_ENTRIES as u32 {
+            sut.add(ModeKind::Big, entry(score * 100));
+        }
+
+        assert_eq!(sut.add(ModeKind::Big, entry(50)), None);
+        assert_eq!(sut.add(ModeKind::Big, entry(550)), Some(6));
+        assert_eq!(sut.get_entries(ModeKind::Big).len(), MAX_ENTRIES);
+        assert_eq!(sut.get_entries(ModeKind::Big).last(), Some(&entry(200)));
+    }
+
+
+    #[test]
+    fn text_is_parsed_back() {
+        let mut sut = HighScores::default();
+        sut.add(ModeKind::Master, entry(1200));
+        sut.add(ModeKind::Pentomino, entry(300));
+
+        assert_eq!(HighScores::parse(&sut.to_text()).unwrap(), sut);
+        assert!(HighScores::parse("marathon: 12 x 1").is_err());
+    }
+}
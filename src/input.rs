@@ -0,0 +1,288 @@
+use std::{thread::{self, JoinHandle}, time::Duration, sync::mpsc::{self, Receiver, Sender, TryRecvError}};
+
+use console::{Term, Key};
+
+use crate::key_bindings::KeyBindings;
+
+// Longest wait of the input thread before it looks for a shutdown
+const POLL_TIME: Duration = Duration::from_millis(100);
+
+// Key pressed by the player, letters keeping their case
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum InputEvent {
+    Char(char),
+    Ctrl(char),
+    Alt(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Space,
+    Escape,
+    Tab,
+    BackTab,
+    Backspace,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown
+}
+
+const NAMED_KEYS: [(InputEvent, &str); 16] = [
+    (InputEvent::Up, "up"),
+    (InputEvent::Down, "down"),
+    (InputEvent::Left, "left"),
+    (InputEvent::Right, "right"),
+    (InputEvent::Enter, "enter"),
+    (InputEvent::Space, "space"),
+    (InputEvent::Escape, "escape"),
+    (InputEvent::Tab, "tab"),
+    (InputEvent::BackTab, "back-tab"),
+    (InputEvent::Backspace, "backspace"),
+    (InputEvent::Delete, "delete"),
+    (InputEvent::Insert, "insert"),
+    (InputEvent::Home, "home"),
+    (InputEvent::End, "end"),
+    (InputEvent::PageUp, "page-up"),
+    (InputEvent::PageDown, "page-down")
+];
+
+impl InputEvent {
+
+    // Control characters are the keys typed with ctrl
+    pub fn from_char(character: char) -> InputEvent {
+        match character {
+            '\n' | '\r' => InputEvent::Enter,
+            ' ' => InputEvent::Space,
+            '\t' => InputEvent::Tab,
+            '\x08' | '\x7f' => InputEvent::Backspace,
+            '\x1B' => InputEvent::Escape,
+            '\x01'..='\x1A' => InputEvent::Ctrl((character as u8 - 1 + b'a') as char),
+            _ => InputEvent::Char(character)
+        }
+    }
+
+
+    // None for keys the terminal could not make sense of
+    pub fn from_key(key: Key) -> Option<InputEvent> {
+        Some(match key {
+            Key::Char(character) => InputEvent::from_char(character),
+            Key::ArrowUp => InputEvent::Up,
+            Key::ArrowDown => InputEvent::Down,
+            Key::ArrowLeft => InputEvent::Left,
+            Key::ArrowRight => InputEvent::Right,
+            Key::Enter => InputEvent::Enter,
+            Key::Escape => InputEvent::Escape,
+            Key::Tab => InputEvent::Tab,
+            Key::BackTab => InputEvent::BackTab,
+            Key::Backspace => InputEvent::Backspace,
+            Key::Del => InputEvent::Delete,
+            Key::Insert => InputEvent::Insert,
+            Key::Home => InputEvent::Home,
+            Key::End => InputEvent::End,
+            Key::PageUp => InputEvent::PageUp,
+            Key::PageDown => InputEvent::PageDown,
+            // Terminals send alt with a key as an escape followed by the key
+            Key::UnknownEscSeq(sequence) => match sequence[..] {
+                [character] => InputEvent::Alt(character),
+                _ => return None
+            },
+            _ => return None
+        })
+    }
+
+
+    pub fn get_name(&self) -> String {
+        match self {
+            InputEvent::Char(character) => character.to_string(),
+            InputEvent::Ctrl(character) => format!("ctrl+{}", character),
+            InputEvent::Alt(character) => format!("alt+{}", character),
+            _ => NAMED_KEYS.iter().find(|(key, _)| key == self).map(|(_, name)| name.to_string()).unwrap_or_default()
+        }
+    }
+
+
+    // A single character, a key name, or a character after "ctrl+" or "alt+"
+    pub fn from_name(name: &str) -> Option<InputEvent> {
+        let mut characters = name.chars();
+        if let (Some(character), None) = (characters.next(), characters.next()) {
+            return Some(InputEvent::from_char(character));
+        }
+        if let Some(named) = NAMED_KEYS.iter().find(|(_, key_name)| *key_name == name) {
+            return Some(named.0);
+        }
+        let (modifier, key) = name.split_once('+')?;
+        let mut characters = key.chars();
+        let character = match (characters.next(), characters.next()) {
+            (Some(character), None) => character,
+            _ => return None
+        };
+        match modifier {
+            // The terminal sends ctrl with a letter as a control character, ctrl+h being a backspace
+            "ctrl" => match character.to_ascii_lowercase() {
+                letter @ 'a'..='z' => Some(InputEvent::from_char((letter as u8 - b'a' + 1) as char)),
+                '[' => Some(InputEvent::Escape),
+                _ => None
+            },
+            "alt" => Some(InputEvent::Alt(character)),
+            _ => None
+        }
+    }
+}
+
+// Keys read by a single thread for the whole program, so menus and games never race for them
+pub struct Input {
+    bindings: KeyBindings,
+    keys: Receiver<InputEvent>,
+    shutdown: Sender<()>,
+    listener: Option<JoinHandle<()>>
+}
+
+impl Input {
+
+    pub fn spawn(bindings: KeyBindings) -> Self {
+        let (to_main, keys) = mpsc::channel::<InputEvent>();
+        let (shutdown, to_stop) = mpsc::channel::<()>();
+        let listener = thread::spawn(move || {
+            let terminal = Term::stdout();
+            // Without a terminal there is no key to read
+            let source = match KeySource::open() {
+                Some(source) if terminal.is_term() => source,
+                _ => return
+            };
+            while let Err(TryRecvError::Empty) = to_stop.try_recv() {
+                if !source.is_key_ready(POLL_TIME) {
+                    continue;
+                }
+                match terminal.read_key().map(InputEvent::from_key) {
+                    Ok(Some(event)) if to_main.send(event).is_err() => break,
+                    Ok(_) => (),
+                    Err(_) => break
+                }
+            }
+        });
+        // A thread that cannot poll may wait for a key forever, so it is left to end with the program
+        Input { bindings, keys, shutdown, listener: Some(listener).filter(|_| KeySource::CAN_POLL) }
+    }
+
+
+    pub fn get_bindings(&self) -> &KeyBindings {
+        &self.bindings
+    }
+
+
+    pub fn set_bindings(&mut self, value: KeyBindings) {
+        self.bindings = value;
+    }
+
+
+    pub fn try_read(&self) -> Option<InputEvent> {
+        self.keys.try_recv().ok()
+    }
+
+
+    // Waits for a key, None once no key can be read anymore
+    pub fn read(&self) -> Option<InputEvent> {
+        self.keys.recv().ok()
+    }
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        let _ = self.shutdown.send(());
+        if let Some(listener) = self.listener.take() {
+            let _ = listener.join();
+        }
+    }
+}
+
+
+// Terminal console reads the keys from: the standard input when it is one, /dev/tty otherwise
+#[cfg(unix)]
+struct KeySource {
+    tty: Option<std::fs::File>
+}
+
+#[cfg(unix)]
+impl KeySource {
+    const CAN_POLL: bool = true;
+
+
+    fn open() -> Option<KeySource> {
+        if unsafe { libc::isatty(libc::STDIN_FILENO) } == 1 {
+            return Some(KeySource { tty: None });
+        }
+        std::fs::File::open("/dev/tty").ok().map(|tty| KeySource { tty: Some(tty) })
+    }
+
+
+    // Waits at most the given time for a key, so the input thread never blocks on a read
+    fn is_key_ready(&self, timeout: Duration) -> bool {
+        use std::os::unix::io::AsRawFd;
+
+        let fd = self.tty.as_ref().map_or(libc::STDIN_FILENO, |tty| tty.as_raw_fd());
+        let mut pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        unsafe { libc::poll(&mut pollfd, 1, timeout.as_millis() as libc::c_int) > 0 }
+    }
+}
+
+
+// Keys are read without waiting first, the read blocking until a key comes
+#[cfg(not(unix))]
+struct KeySource;
+
+#[cfg(not(unix))]
+impl KeySource {
+    const CAN_POLL: bool = false;
+
+
+    fn open() -> Option<KeySource> {
+        Some(KeySource)
+    }
+
+
+    fn is_key_ready(&self, _timeout: Duration) -> bool {
+        true
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use console::Key;
+
+    use super::InputEvent;
+
+    #[test]
+    fn keys_are_named_with_their_modifiers() {
+        for name in ["a", "A", "\"", "space", "enter", "up", "escape", "page-down", "ctrl+c", "alt+x"] {
+            assert_eq!(InputEvent::from_name(name).unwrap().get_name(), name);
+        }
+        assert_eq!(InputEvent::from_name("ctrl+C"), Some(InputEvent::Ctrl('c')));
+        assert_eq!(InputEvent::from_name("shift+a"), None);
+    }
+
+
+    #[test]
+    fn ctrl_keys_are_those_the_terminal_sends() {
+        assert_eq!(InputEvent::from_name("ctrl+h"), Some(InputEvent::Backspace));
+        assert_eq!(InputEvent::from_name("ctrl+i"), Some(InputEvent::Tab));
+        assert_eq!(InputEvent::from_name("ctrl+j"), Some(InputEvent::Enter));
+        assert_eq!(InputEvent::from_name("ctrl+m"), Some(InputEvent::Enter));
+        assert_eq!(InputEvent::from_name("ctrl+["), Some(InputEvent::Escape));
+        assert_eq!(InputEvent::from_name("ctrl+1"), None);
+    }
+
+
+    #[test]
+    fn special_keys_of_the_terminal_are_events() {
+        assert_eq!(InputEvent::from_key(Key::ArrowLeft), Some(InputEvent::Left));
+        assert_eq!(InputEvent::from_key(Key::Char('\x03')), Some(InputEvent::Ctrl('c')));
+        assert_eq!(InputEvent::from_key(Key::UnknownEscSeq(vec!['x'])), Some(InputEvent::Alt('x')));
+        assert_eq!(InputEvent::from_key(Key::Unknown), None);
+    }
+}
@@ -0,0 +1,377 @@
+use std::{fmt::Display, fs, iter::Peekable, path::{Path, PathBuf}, str::Chars};
+
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::{gameboard::{Action, MovementDirection}, menu::MenuInput, input::InputEvent};
+
+#[derive(Debug)]
+pub struct BindingError(String);
+
+impl Display for BindingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+
+#[derive(EnumIter, Copy, Clone, PartialEq, Debug)]
+pub enum Control {
+    Left,
+    Right,
+    SoftDrop,
+    HardDrop,
+    Rotate,
+    Hold,
+    Pause,
+    Quit
+}
+
+impl Control {
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Control::Left => "left",
+            Control::Right => "right",
+            Control::SoftDrop => "soft-drop",
+            Control::HardDrop => "hard-drop",
+            Control::Rotate => "rotate",
+            Control::Hold => "hold",
+            Control::Pause => "pause",
+            Control::Quit => "quit"
+        }
+    }
+
+
+    pub fn from_name(name: &str) -> Option<Control> {
+        Control::iter().find(|control| control.get_name() == name)
+    }
+
+
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            Control::Left => "Move left",
+            Control::Right => "Move right",
+            Control::SoftDrop => "Soft drop",
+            Control::HardDrop => "Hard drop",
+            Control::Rotate => "Rotate",
+            Control::Hold => "Hold",
+            Control::Pause => "Pause",
+            Control::Quit => "Quit"
+        }
+    }
+}
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameInput {
+    Play(Action),
+    Pause,
+    Quit
+}
+
+
+#[derive(EnumIter, Copy, Clone, PartialEq, Debug)]
+pub enum Preset {
+    Azerty,
+    Qwerty,
+    Vim
+}
+
+impl Preset {
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Preset::Azerty => "azerty",
+            Preset::Qwerty => "qwerty",
+            Preset::Vim => "vim"
+        }
+    }
+
+
+    pub fn from_name(name: &str) -> Option<Preset> {
+        Preset::iter().find(|preset| preset.get_name() == name)
+    }
+
+
+    // Keys of every control, in the order of the controls, arrow keys working with all of them
+    fn get_keys(&self) -> [&'static [&'static str]; 8] {
+        match self {
+            Preset::Azerty => [&["q", "left"], &["d", "right"], &["s", "down"], &["space"], &["z", "up"], &["enter"], &["p", "escape"], &["x"]],
+            Preset::Qwerty => [&["a", "left"], &["d", "right"], &["s", "down"], &["space"], &["w", "up"], &["c"], &["p", "escape"], &["x"]],
+            Preset::Vim => [&["h", "left"], &["l", "right"], &["j", "down"], &["space"], &["k", "up"], &["y"], &["p", "escape"], &["q"]]
+        }
+    }
+
+
+    pub fn get_bindings(&self) -> KeyBindings {
+        let keys = Control::iter().zip(self.get_keys())
+            .map(|(control, names)| (control, names.iter().map(|name| InputEvent::from_name(name).expect("Preset keys are valid")).collect()))
+            .collect();
+        KeyBindings { keys }
+    }
+}
+
+
+/*
+ * Key bindings are read from a TOML file, a preset then the keys of some controls replacing its own :
+ *
+ *   preset = "qwerty"
+ *
+ *   [keys]
+ *   rotate = ["w", "up"]        (one key or a list of keys)
+ *   hold = "ctrl+f"
+ *
+ * Keys are characters, names like "space", "enter" or "escape", or a character after "ctrl+" or "alt+".
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct KeyBindings {
+    keys: Vec<(Control, Vec<InputEvent>)>
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Preset::Azerty.get_bindings()
+    }
+}
+
+impl KeyBindings {
+
+    // In the configuration directory of the user, e.g. ~/.config/tetrust on Linux
+    pub fn get_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|directory| directory.join("tetrust").join("keys.toml"))
+    }
+
+
+    // No file yet gives the default bindings
+    pub fn load(path: &Path) -> Result<KeyBindings, BindingError> {
+        if !path.exists() {
+            return Ok(KeyBindings::default());
+        }
+        let text = fs::read_to_string(path)
+            .map_err(|error| BindingError(format!("Cannot read {} : {}", path.display(), error)))?;
+        KeyBindings::parse(&text).map_err(|error| BindingError(format!("{} : {}", path.display(), error)))
+    }
+
+
+    pub fn save(&self, path: &Path) -> Result<(), BindingError> {
+        let error = |error: std::io::Error| BindingError(format!("Cannot write {} : {}", path.display(), error));
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(error)?;
+        }
+        fs::write(path, self.to_text()).map_err(error)
+    }
+
+
+    pub fn parse(text: &str) -> Result<KeyBindings, BindingError> {
+        let mut preset = Preset::Azerty;
+        let mut keys = vec![];
+        let mut section = String::new();
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.split('#').next()?.trim().strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+
+            let (name, value) = line.split_once('=').ok_or(BindingError(format!("Expected \"<name> = <value>\", found : {}", line)))?;
+            let (name, value) = (name.trim(), parse_value(value).ok_or(BindingError(format!("Expected a string or a list of strings, found : {}", line)))?);
+            match (section.as_str(), name, &value[..]) {
+                ("", "preset", [value]) => preset = Preset::from_name(value).ok_or(BindingError(format!("Unknown preset : {}", value)))?,
+                ("keys", name, values) => {
+                    let control = Control::from_name(name).ok_or(BindingError(format!("Unknown control : {}", name)))?;
+                    let values = values.iter()
+                        .map(|value| InputEvent::from_name(value).ok_or(BindingError(format!("Unknown key : {}", value))))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    keys.push((control, values));
+                },
+                _ => return Err(BindingError(format!("Unexpected line : {}", line)))
+            }
+        }
+
+        let mut bindings = preset.get_bindings();
+        for (control, keys) in keys {
+            bindings.set_keys(control, keys);
+        }
+        Ok(bindings)
+    }
+
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# Key bindings of Tetrust\n\n[keys]\n");
+        for (control, keys) in self.keys.iter() {
+            let names: Vec<String> = keys.iter().map(|key| format!("\"{}\"", key.get_name().replace('\\', "\\\\").replace('"', "\\\""))).collect();
+            text.push_str(&format!("{} = [{}]\n", control.get_name(), names.join(", ")));
+        }
+        text
+    }
+
+
+    pub fn get_keys(&self, control: Control) -> &[InputEvent] {
+        self.keys.iter().find(|(bound, _)| *bound == control).map_or(&[], |(_, keys)| keys)
+    }
+
+
+    pub fn set_keys(&mut self, control: Control, keys: Vec<InputEvent>) {
+        if let Some(bound) = self.keys.iter_mut().find(|(bound, _)| *bound == control) {
+            bound.1 = keys;
+        }
+    }
+
+
+    // The key is taken away from any other control, so a key never does two things
+    pub fn bind(&mut self, control: Control, key: InputEvent) {
+        for (_, keys) in self.keys.iter_mut() {
+            keys.retain(|bound| *bound != key);
+        }
+        self.set_keys(control, vec![key]);
+    }
+
+
+    pub fn get_preset(&self) -> Option<Preset> {
+        Preset::iter().find(|preset| preset.get_bindings() == *self)
+    }
+
+
+    pub fn get_control(&self, key: InputEvent) -> Option<Control> {
+        self.keys.iter().find(|(_, keys)| keys.contains(&key)).map(|(control, _)| *control)
+    }
+
+
+    // Ctrl-C quits whatever the bindings
+    pub fn is_quit(&self, key: InputEvent) -> bool {
+        key == InputEvent::Ctrl('c') || self.get_control(key) == Some(Control::Quit)
+    }
+
+
+    pub fn get_game_input(&self, key: InputEvent) -> Option<GameInput> {
+        if self.is_quit(key) {
+            return Some(GameInput::Quit);
+        }
+        Some(match self.get_control(key)? {
+            Control::Left => GameInput::Play(Action::Move(MovementDirection::Left)),
+            Control::Right => GameInput::Play(Action::Move(MovementDirection::Right)),
+            Control::SoftDrop => GameInput::Play(Action::Move(MovementDirection::Bottom)),
+            Control::HardDrop => GameInput::Play(Action::Move(MovementDirection::Top)),
+            Control::Rotate => GameInput::Play(Action::Rotate),
+            Control::Hold => GameInput::Play(Action::Hold),
+            Control::Pause => GameInput::Pause,
+            Control::Quit => GameInput::Quit
+        })
+    }
+
+
+    // Menus are driven with the game controls, rotate going up and soft drop going down
+    pub fn get_menu_input(&self, key: InputEvent) -> Option<MenuInput> {
+        if key == InputEvent::Enter || key == InputEvent::Space {
+            return Some(MenuInput::Confirm);
+        }
+        match self.get_control(key)? {
+            Control::Rotate => Some(MenuInput::Up),
+            Control::SoftDrop => Some(MenuInput::Down),
+            Control::Left => Some(MenuInput::Previous),
+            Control::Right => Some(MenuInput::Next),
+            Control::HardDrop | Control::Hold => Some(MenuInput::Confirm),
+            _ => None
+        }
+    }
+
+
+    // First key of the control, shown in hints
+    pub fn get_key_name(&self, control: Control) -> String {
+        self.get_keys(control).first().map_or(String::from("-"), InputEvent::get_name)
+    }
+}
+
+
+// A string or an array of strings, a comment being allowed after it
+fn parse_value(value: &str) -> Option<Vec<String>> {
+    let mut characters = value.trim().chars().peekable();
+    let is_array = characters.next_if_eq(&'[').is_some();
+    let mut strings = vec![];
+    loop {
+        skip_spaces(&mut characters);
+        if is_array && characters.next_if_eq(&']').is_some() {
+            break;
+        }
+        strings.push(parse_string(&mut characters)?);
+        skip_spaces(&mut characters);
+        if !is_array {
+            break;
+        }
+        if characters.next_if_eq(&',').is_none() {
+            characters.next_if_eq(&']')?;
+            break;
+        }
+    }
+    skip_spaces(&mut characters);
+    match characters.next() {
+        None | Some('#') => Some(strings),
+        _ => None
+    }
+}
+
+
+fn skip_spaces(characters: &mut Peekable<Chars>) {
+    while characters.next_if(|character| character.is_whitespace()).is_some() {}
+}
+
+
+fn parse_string(characters: &mut Peekable<Chars>) -> Option<String> {
+    characters.next_if_eq(&'"')?;
+    let mut string = String::new();
+    loop {
+        match characters.next()? {
+            '"' => return Some(string),
+            '\\' => string.push(characters.next()?),
+            character => string.push(character)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use strum::IntoEnumIterator;
+
+    use crate::{gameboard::Action, menu::MenuInput, input::InputEvent};
+
+    use super::{KeyBindings, Control, Preset, GameInput};
+
+    #[test]
+    fn keys_of_the_file_replace_those_of_the_preset() {
+        let text = "# comment\npreset = \"qwerty\"\n\n[keys]\nrotate = [\"k\", \"up\"] # vim like\nhold = \"ctrl+f\"\n";
+
+        let sut = KeyBindings::parse(text).unwrap();
+
+        assert_eq!(sut.get_keys(Control::Rotate), &[InputEvent::Char('k'), InputEvent::Up]);
+        assert_eq!(sut.get_keys(Control::Hold), &[InputEvent::Ctrl('f')]);
+        assert_eq!(sut.get_keys(Control::Left), &[InputEvent::Char('a'), InputEvent::Left]);
+        assert!(KeyBindings::parse("[keys]\nspin = \"z\"").is_err());
+        assert!(KeyBindings::parse("[keys]\nhold = [\"z\"").is_err());
+    }
+
+
+    #[test]
+    fn presets_are_saved_and_parsed_back() {
+        for preset in Preset::iter() {
+            let sut = preset.get_bindings();
+
+            assert_eq!(KeyBindings::parse(&sut.to_text()).unwrap().get_preset(), Some(preset));
+        }
+    }
+
+
+    #[test]
+    fn rebinding_a_key_takes_it_from_other_controls() {
+        let mut sut = KeyBindings::default();
+
+        sut.bind(Control::Hold, InputEvent::Char('z'));
+
+        assert_eq!(sut.get_game_input(InputEvent::Char('z')), Some(GameInput::Play(Action::Hold)));
+        assert_eq!(sut.get_keys(Control::Rotate), &[InputEvent::Up]);
+        assert_eq!(sut.get_menu_input(InputEvent::Char('s')), Some(MenuInput::Down));
+        assert_eq!(sut.get_game_input(InputEvent::Ctrl('c')), Some(GameInput::Quit));
+        assert_eq!(sut.get_preset(), None);
+    }
+}
@@ -0,0 +1,197 @@
+use console::measure_text_width;
+
+// Blank columns or rows between composed panels
+const GAP: usize = 3;
+
+// Block of terminal lines, as wide as its widest line, styles included but not counted
+#[derive(Clone, PartialEq, Debug)]
+pub struct Panel {
+    lines: Vec<String>,
+    width: usize
+}
+
+impl Panel {
+
+    pub fn new(lines: Vec<String>) -> Self {
+        Panel {
+            width: lines.iter().map(|line| measure_text_width(line)).max().unwrap_or(0),
+            lines
+        }
+    }
+
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+
+    pub fn get_height(&self) -> usize {
+        self.lines.len()
+    }
+
+
+    pub fn with_title(self, title: String) -> Panel {
+        Panel::new(std::iter::once(title).chain(self.lines).collect())
+    }
+
+
+    // Both panels side by side, aligned on their top line
+    pub fn beside(self, right: Panel) -> Panel {
+        let height = usize::max(self.get_height(), right.get_height());
+        let lines = (0..height).map(|index| {
+            let left = self.lines.get(index).map_or("", String::as_str);
+            let padding = self.width + GAP - measure_text_width(left);
+            let right = right.lines.get(index).map_or("", String::as_str);
+            format!("{}{}{}", left, " ".repeat(padding), right).trim_end().to_string()
+        });
+        Panel::new(lines.collect())
+    }
+
+
+    // Both panels one above the other, a blank line between them
+    pub fn above(self, below: Panel) -> Panel {
+        if self.lines.is_empty() || below.lines.is_empty() {
+            return Panel::new(self.lines.into_iter().chain(below.lines).collect());
+        }
+        Panel::new(self.lines.into_iter().chain(std::iter::once(String::new())).chain(below.lines).collect())
+    }
+
+
+    // Centered in a terminal of the given rows and columns, lines ending with "\n\r"
+    pub fn center_in(&self, rows: usize, columns: usize) -> String {
+        let margin = " ".repeat(columns.saturating_sub(self.width) / 2);
+        let mut text = "\n\r".repeat(rows.saturating_sub(self.get_height()) / 2);
+        for line in self.lines.iter() {
+            text.push_str(&format!("{}{}\n\r", margin, line));
+        }
+        text
+    }
+}
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Placement {
+    Left,
+    Right,
+    Above,
+    Below
+}
+
+// Panels placed around a central one, panels sharing a side being stacked in the order they were placed
+pub struct Layout {
+    center: Panel,
+    panels: Vec<(Placement, Panel)>
+}
+
+impl Layout {
+
+    pub fn new(center: Panel) -> Self {
+        Layout {
+            center,
+            panels: vec![]
+        }
+    }
+
+
+    pub fn place(&mut self, placement: Placement, panel: Panel) {
+        self.panels.push((placement, panel));
+    }
+
+
+    fn stack(&self, placement: Placement) -> Option<Panel> {
+        self.panels.iter()
+            .filter(|(placed, _)| *placed == placement)
+            .map(|(_, panel)| panel.clone())
+            .reduce(Panel::above)
+    }
+
+
+    // Side panels go under the central one, the right ones first, until everything fits the width
+    pub fn compose(&self, columns: usize) -> Panel {
+        let (left, right) = (self.stack(Placement::Left), self.stack(Placement::Right));
+        let candidates = [
+            (left.clone(), right.clone(), vec![]),
+            (left.clone(), None, right.iter().cloned().collect()),
+            (None, None, left.iter().chain(right.iter()).cloned().collect::<Vec<_>>())
+        ];
+
+        let mut composed = None;
+        for (left, right, under) in candidates {
+            let mut row = self.center.clone();
+            if let Some(left) = left {
+                row = left.beside(row);
+            }
+            if let Some(right) = right {
+                row = row.beside(right);
+            }
+            // Panels moved under the central one are put side by side when they fit
+            let under = under.into_iter().reduce(|first, second| match first.get_width() + GAP + second.get_width() <= columns {
+                true => first.beside(second),
+                false => first.above(second)
+            });
+            let fits = row.get_width() <= columns;
+            composed = Some(match under {
+                Some(under) => row.above(under),
+                None => row
+            });
+            if fits {
+                break;
+            }
+        }
+
+        let mut composed = composed.unwrap();
+        if let Some(above) = self.stack(Placement::Above) {
+            composed = above.above(composed);
+        }
+        if let Some(below) = self.stack(Placement::Below) {
+            composed = composed.above(below);
+        }
+        composed
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{Panel, Layout, Placement, GAP};
+
+    fn panel(width: usize, height: usize) -> Panel {
+        Panel::new(vec!["x".repeat(width); height])
+    }
+
+    #[test]
+    fn styles_are_not_counted_in_the_width() {
+        let sut = Panel::new(vec![String::from("\x1B[1mbold\x1B[0m"), String::from("ab")]);
+
+        assert_eq!(sut.get_width(), 4);
+    }
+
+
+    #[test]
+    fn side_panels_surround_the_center_when_they_fit() {
+        let mut sut = Layout::new(panel(20, 10));
+        sut.place(Placement::Left, panel(8, 3));
+        sut.place(Placement::Right, panel(8, 12));
+        sut.place(Placement::Above, panel(5, 2));
+
+        let result = sut.compose(80);
+
+        assert_eq!(result.get_width(), 8 + GAP + 20 + GAP + 8);
+        assert_eq!(result.get_height(), 2 + 1 + 12);
+    }
+
+
+    #[test]
+    fn side_panels_go_under_the_center_in_narrow_terminals() {
+        let mut sut = Layout::new(panel(20, 10));
+        sut.place(Placement::Left, panel(8, 3));
+        sut.place(Placement::Right, panel(8, 4));
+
+        let medium = sut.compose(35);
+        let narrow = sut.compose(20);
+
+        assert_eq!((medium.get_width(), medium.get_height()), (8 + GAP + 20, 10 + 1 + 4));
+        assert_eq!((narrow.get_width(), narrow.get_height()), (20, 10 + 1 + 4));
+        assert_eq!(sut.compose(10).get_height(), 10 + 1 + 3 + 1 + 4);
+    }
+}
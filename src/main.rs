@@ -1,14 +1,82 @@
 mod graphics;
+mod scene;
+#[cfg(feature = "3d")]
+mod graphics_3d;
 mod game_manager;
 mod piece;
 mod gameboard;
 mod piece_provider;
 mod piece_factory;
+mod game_mode;
+mod master_mode;
+mod puzzle_mode;
+mod cli;
+mod scoring;
+mod game_event;
+mod game_stats;
+mod finesse;
+mod game;
+mod replay;
+mod snapshot;
+mod gif_export;
+mod cell_grid;
+mod board_export;
+mod theme;
+mod layout;
+mod animation;
+mod input;
+mod menu;
+mod high_scores;
+mod terminal;
+mod key_bindings;
 
 
+use std::path::Path;
+
+use cli::Command;
 use game_manager::GameManager;
+use replay::Replay;
+use snapshot::PngVisualizer;
 
 fn main() {
-    
-    GameManager::start();
+
+    let command = match cli::parse_command(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+
+    match command {
+        Command::Play(options) => GameManager::start(&options),
+        Command::Snapshot { replay, frame, output } => {
+            if let Err(error) = snapshot(&replay, frame, output) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        },
+        Command::Export { replay, frame, every, output } => {
+            if let Err(error) = board_export::export_file(&replay, frame, every, &output) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        },
+        Command::Gif { replay, options } => {
+            match gif_export::export_gif_file(&replay, &options) {
+                Ok(pictures) => println!("{} pictures written to {}", pictures, options.output.display()),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
+
+
+// Board of a replay at the given frame, or once it ended, written to a PNG file
+fn snapshot(replay: &Path, frame: Option<u32>, output: std::path::PathBuf) -> Result<(), String> {
+    let game = Replay::load(replay).map_err(|error| error.to_string())?.play(frame);
+    PngVisualizer::new(output).save(game.get_board()).map_err(|error| error.to_string())
 }
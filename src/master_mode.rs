@@ -0,0 +1,205 @@
+use crate::{gameboard::{GameBoard, BoardRules}, game_mode::{GameMode, FRAME_RATE}, scoring::ScoringKind};
+
+// Gravity is given in 1/256 G as in TGM, one G being one row per frame
+const GRAVITY_DENOMINATOR: f32 = 256.0;
+const GRAVITY_TABLE: [(u32, u32); 30] = [
+    (0, 4), (30, 6), (35, 8), (40, 10), (50, 12), (60, 16), (70, 32), (80, 48),
+    (90, 64), (100, 80), (120, 96), (140, 112), (160, 128), (170, 144), (200, 4),
+    (220, 32), (230, 64), (233, 96), (236, 128), (239, 160), (243, 192), (247, 224),
+    (251, 256), (300, 512), (330, 768), (360, 1024), (400, 1280), (420, 1024),
+    (450, 768), (500, 5120)
+];
+
+const SECTION_SIZE: u32 = 100;
+const SECTION_LOCK_FRAMES: [u32; 10] = [30, 30, 30, 30, 30, 27, 24, 21, 18, 17];
+const MAX_LEVEL: u32 = 999;
+
+// A section cleared under these times earns two or one grade steps
+const COOL_SECTION_TIME: f32 = 55.0;
+const REGULAR_SECTION_TIME: f32 = 80.0;
+const GRADES: [&str; 19] = [
+    "9", "8", "7", "6", "5", "4", "3", "2", "1",
+    "S1", "S2", "S3", "S4", "S5", "S6", "S7", "S8", "S9", "GM"
+];
+
+pub struct MasterMode {
+    level: u32,
+    pieces_placed: u32,
+    lines_cleared: u32,
+    section_time: f32,
+    section_times: Vec<f32>
+}
+
+impl MasterMode {
+
+    pub fn new() -> Self {
+        MasterMode {
+            level: 0,
+            pieces_placed: 0,
+            lines_cleared: 0,
+            section_time: 0.0,
+            section_times: vec![]
+        }
+    }
+
+
+    pub fn get_grade(&self) -> &'static str {
+        let steps = self.section_times.iter().map(|time| {
+            if *time < COOL_SECTION_TIME {
+                2
+            } else if *time < REGULAR_SECTION_TIME {
+                1
+            } else {
+                0
+            }
+        }).sum::<usize>();
+        GRADES[usize::min(steps, GRADES.len() - 1)]
+    }
+
+
+    // A placed piece cannot cross a section stop (x99), only line clears can
+    fn advance(&mut self, amount: u32, from_lines: bool) {
+        for _ in 0..amount {
+            let at_section_stop = self.level % SECTION_SIZE == SECTION_SIZE - 1 ||
+                self.level == MAX_LEVEL - 1;
+            if self.level >= MAX_LEVEL || (at_section_stop && !from_lines) {
+                return;
+            }
+
+            self.level += 1;
+            if self.level.is_multiple_of(SECTION_SIZE) || self.level == MAX_LEVEL {
+                self.section_times.push(self.section_time);
+                self.section_time = 0.0;
+            }
+        }
+    }
+}
+
+impl GameMode for MasterMode {
+
+    // The level grows with every piece, only new sections are worth a banner
+    fn get_rules(&self) -> BoardRules {
+        BoardRules { level_section: SECTION_SIZE, ..BoardRules::default() }
+    }
+
+
+    // Master follows the arcade games, which came from Sega's
+    fn get_scoring(&self) -> ScoringKind {
+        ScoringKind::Sega
+    }
+
+
+    fn get_gravity(&self) -> Option<f32> {
+        let (_, gravity) = GRAVITY_TABLE.iter()
+            .rev()
+            .find(|(level, _)| *level <= self.level)
+            .unwrap();
+        Some(*gravity as f32 / GRAVITY_DENOMINATOR * FRAME_RATE)
+    }
+
+
+    fn get_lock_delay(&self) -> Option<f32> {
+        let section = (self.level / SECTION_SIZE) as usize;
+        let frames = SECTION_LOCK_FRAMES[usize::min(section, SECTION_LOCK_FRAMES.len() - 1)];
+        Some(frames as f32 / FRAME_RATE)
+    }
+
+
+    fn update(&mut self, board: &mut GameBoard, time_delta: f32) {
+        self.section_time += time_delta;
+
+        let placed = board.get_pieces_placed() - self.pieces_placed;
+        let cleared = board.get_lines_cleared() - self.lines_cleared;
+        self.pieces_placed = board.get_pieces_placed();
+        self.lines_cleared = board.get_lines_cleared();
+
+        self.advance(placed, false);
+        self.advance(cleared, true);
+        board.set_level(u32::max(self.level, 1));
+    }
+
+
+    fn is_finished(&self) -> bool {
+        self.level >= MAX_LEVEL
+    }
+
+
+    fn get_result(&self) -> Option<String> {
+        Some(format!("Grade : {}", self.get_grade()))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{game_mode::{GameMode, INSTANT_GRAVITY}, gameboard::GameBoard, game_event::GameEvent};
+
+    use super::MasterMode;
+
+    #[test]
+    fn gravity_reaches_20g_at_level_500() {
+        let mut sut = MasterMode::new();
+        assert!(sut.get_gravity().unwrap() < INSTANT_GRAVITY);
+
+        sut.level = 500;
+
+        assert!(sut.get_gravity().unwrap() >= INSTANT_GRAVITY);
+    }
+
+
+    #[test]
+    fn placed_pieces_stop_at_section_end_but_line_clears_do_not() {
+        let mut sut = MasterMode::new();
+
+        sut.advance(150, false);
+        assert_eq!(sut.level, 99);
+
+        sut.advance(2, true);
+        assert_eq!(sut.level, 101);
+        assert_eq!(sut.section_times.len(), 1);
+    }
+
+
+    #[test]
+    fn only_new_sections_are_announced() {
+        let sut = MasterMode::new();
+        let mut board = GameBoard::new(ChaCha8Rng::seed_from_u64(1), 1);
+        board.set_rules(sut.get_rules());
+        board.drain_events();
+
+        for level in [2, 50, 99, 101, 150] {
+            board.set_level(level);
+        }
+
+        assert_eq!(board.drain_events(), vec![GameEvent::LevelUp(101)]);
+    }
+
+
+    #[test]
+    fn lock_delay_shrinks_in_late_sections() {
+        let mut sut = MasterMode::new();
+        let early_delay = sut.get_lock_delay().unwrap();
+
+        sut.level = 900;
+
+        assert!(sut.get_lock_delay().unwrap() < early_delay);
+    }
+
+
+    #[test]
+    fn cool_sections_earn_grand_master() {
+        let mut sut = MasterMode::new();
+        assert_eq!(sut.get_grade(), "9");
+
+        for _ in 0..10 {
+            sut.section_time = 40.0;
+            sut.advance(100, true);
+        }
+
+        assert!(sut.is_finished());
+        assert_eq!(sut.get_grade(), "GM");
+    }
+}
@@ -0,0 +1,137 @@
+use colored::Colorize;
+use console::Term;
+
+use crate::layout::Panel;
+
+// What the keys do in menus, see KeyBindings::get_menu_input
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Previous,
+    Next,
+    Confirm
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MenuAction {
+    Select(usize),
+    // Value of an item changed to the previous (-1) or next (1) one
+    Change(usize, isize),
+    None
+}
+
+// Keyboard driven list of items, some of them showing a value changed with left and right
+pub struct Menu {
+    title: String,
+    // Lines shown between the title and the items
+    text: Vec<String>,
+    items: Vec<(String, Option<String>)>,
+    selected: usize
+}
+
+impl Menu {
+
+    pub fn new(title: &str, labels: &[&str]) -> Self {
+        Menu {
+            title: title.to_string(),
+            text: vec![],
+            items: labels.iter().map(|label| (label.to_string(), None)).collect(),
+            selected: 0
+        }
+    }
+
+
+    pub fn with_text(mut self, text: Vec<String>) -> Self {
+        self.text = text;
+        self
+    }
+
+
+    pub fn set_selected(&mut self, value: usize) {
+        self.selected = usize::min(value, self.items.len() - 1);
+    }
+
+
+    pub fn set_value(&mut self, index: usize, value: String) {
+        self.items[index].1 = Some(value);
+    }
+
+
+    pub fn handle(&mut self, input: MenuInput) -> MenuAction {
+        let count = self.items.len();
+        let has_value = self.items[self.selected].1.is_some();
+        match input {
+            MenuInput::Up => self.selected = (self.selected + count - 1) % count,
+            MenuInput::Down => self.selected = (self.selected + 1) % count,
+            MenuInput::Previous if has_value => return MenuAction::Change(self.selected, -1),
+            MenuInput::Next if has_value => return MenuAction::Change(self.selected, 1),
+            MenuInput::Confirm => return MenuAction::Select(self.selected),
+            _ => ()
+        }
+        MenuAction::None
+    }
+
+
+    pub fn render(&self) -> Panel {
+        let mut lines = vec![self.title.bold().to_string(), String::new()];
+        if !self.text.is_empty() {
+            lines.extend(self.text.iter().cloned());
+            lines.push(String::new());
+        }
+
+        let label_width = self.items.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+        for (index, (label, value)) in self.items.iter().enumerate() {
+            let item = match value {
+                Some(value) => format!("{:<width$}   < {} >", label, value, width = label_width),
+                None => label.clone()
+            };
+            lines.push(match index == self.selected {
+                true => format!("> {}", item).reversed().to_string(),
+                false => format!("  {}", item)
+            });
+        }
+        Panel::new(lines)
+    }
+}
+
+
+// Replaces whatever the terminal shows with the panel, centered
+pub fn show(panel: &Panel) {
+    let (rows, columns) = Term::stdout().size();
+    show_text(&panel.center_in(rows as usize, columns as usize));
+}
+
+
+pub fn show_text(text: &str) {
+    print!("\x1B[2J\x1B[H{}", text);
+    let _ = std::io::Write::flush(&mut std::io::stdout());
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{Menu, MenuAction, MenuInput};
+
+    #[test]
+    fn selection_wraps_around_the_items() {
+        let mut sut = Menu::new("Title", &["Play", "Settings", "Quit"]);
+
+        assert_eq!(sut.handle(MenuInput::Up), MenuAction::None);
+        assert_eq!(sut.selected, 2);
+        sut.handle(MenuInput::Down);
+        assert_eq!(sut.handle(MenuInput::Confirm), MenuAction::Select(0));
+    }
+
+
+    #[test]
+    fn only_items_with_a_value_can_be_changed() {
+        let mut sut = Menu::new("Settings", &["Hold", "Back"]);
+        sut.set_value(0, String::from("off"));
+
+        assert_eq!(sut.handle(MenuInput::Next), MenuAction::Change(0, 1));
+        sut.handle(MenuInput::Down);
+        assert_eq!(sut.handle(MenuInput::Next), MenuAction::None);
+        assert_eq!(sut.render().get_height(), 2 + 2);
+    }
+}
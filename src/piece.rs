@@ -1,13 +1,12 @@
-extern crate kiss3d;
-
 use std::{hash::Hasher, hash::Hash};
 use std::fmt::Debug;
 
 use crate::{piece_factory::{PieceFactory, PieceType}};
 
-use kiss3d::nalgebra::{Vector2};
+use nalgebra::Vector2;
+use strum_macros::EnumIter;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(EnumIter, Copy, Clone, PartialEq, Debug)]
 pub enum Color {
     Cyan, 
     DarkBlue,
@@ -15,7 +14,39 @@ pub enum Color {
     Yellow,
     Green,
     Purple, 
-    Red
+    Red,
+    Gray
+}
+
+impl Color {
+
+    pub fn from_name(name: &str) -> Option<Color> {
+        match name {
+            "cyan" => Some(Color::Cyan),
+            "dark-blue" => Some(Color::DarkBlue),
+            "orange" => Some(Color::Orange),
+            "yellow" => Some(Color::Yellow),
+            "green" => Some(Color::Green),
+            "purple" => Some(Color::Purple),
+            "red" => Some(Color::Red),
+            "gray" => Some(Color::Gray),
+            _ => None
+        }
+    }
+
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Color::Cyan => "cyan",
+            Color::DarkBlue => "dark-blue",
+            Color::Orange => "orange",
+            Color::Yellow => "yellow",
+            Color::Green => "green",
+            Color::Purple => "purple",
+            Color::Red => "red",
+            Color::Gray => "gray"
+        }
+    }
 }
 
 
@@ -30,7 +61,10 @@ fn rotate_90_cw_around(point: &Vector2<isize>, pivot: Vector2<isize>) -> Vector2
 #[derive(Copy, Clone)]
 pub struct Square {
     position: Vector2<isize>,
-    color: Color
+    color: Color,
+    lock_time: Option<f32>,
+    // None for squares not dealt as part of a piece, like puzzle garbage
+    piece_type: Option<PieceType>
 }
 
 impl Square {
@@ -38,18 +72,23 @@ impl Square {
     pub fn new(position: Vector2<isize>, color: Color) -> Self {
         Square {
             position,
-            color
+            color,
+            lock_time: None,
+            piece_type: None
         }
     }
 
 
-    pub fn get_position(&self) -> Vector2<isize>{
-        self.position
+    pub fn of_piece(position: Vector2<isize>, color: Color, piece_type: PieceType) -> Self {
+        Square {
+            piece_type: Some(piece_type),
+            ..Square::new(position, color)
+        }
     }
 
 
-    pub fn get_position_mut(&mut self) -> &mut Vector2<isize> {
-        &mut self.position
+    pub fn get_position(&self) -> Vector2<isize>{
+        self.position
     }
 
 
@@ -63,24 +102,37 @@ impl Square {
     }
 
 
+    pub fn get_piece_type(&self) -> Option<PieceType> {
+        self.piece_type
+    }
+
+
+    // Board time at which the square was locked, None while it belongs to a falling piece
+    pub fn get_lock_time(&self) -> Option<f32> {
+        self.lock_time
+    }
+
+
+    pub fn set_lock_time(&mut self, value: f32) {
+        self.lock_time = Some(value);
+    }
+
+
     pub fn get_rotated_position(&self, rotation_type: RotationType, pivot: Vector2<isize>) -> Vector2<isize> {
         match rotation_type {
             RotationType::AroundSquare => 
                 rotate_90_cw_around(&self.position, pivot),
             RotationType::AroundPoint(point) => 
-                rotate_90_cw_around(&self.position, point) + Vector2::new(1,0)
+                rotate_90_cw_around(&self.position, point) + Vector2::new(1,0),
+            RotationType::None => self.position
         }
     }
 
 
     pub fn translate(&mut self, translation: Vector2<isize>) {
-        self.position = self.position + translation;
+        self.position += translation;
     }
 
-
-    pub fn move_at(&mut self, position: Vector2<isize>) {
-        self.position = position;
-    }
 }
 
 
@@ -90,49 +142,55 @@ impl Debug for Square {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+// AroundSquare turns around the first square, AroundPoint around the corner
+// shared by the square at the point and the one below its right neighbour
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum RotationType {
     AroundSquare,
-    AroundPoint(Vector2<isize>)
+    AroundPoint(Vector2<isize>),
+    None
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Piece {
-    squares: [Square; 4],
+    squares: Vec<Square>,
     rotation: RotationType,
+    // Offsets tried in order when the rotated piece collides
+    kicks: Vec<Vector2<isize>>,
     piece_type: PieceType,
     rotation_position: u8
 }
 
 impl Piece {
 
-    pub fn new(squares: [Square; 4], rotation: RotationType, piece_type: PieceType) -> Self {
+    pub fn new(squares: Vec<Square>, rotation: RotationType, kicks: Vec<Vector2<isize>>, piece_type: PieceType) -> Self {
         Piece {
             squares,
             rotation,
+            kicks,
             piece_type,
             rotation_position: 0
         }
     }
 
 
-    pub fn get_squares(&self) -> &[Square; 4]{
+    pub fn get_squares(&self) -> &[Square]{
         &self.squares
     }
 
 
-    pub fn get_squares_mut(&mut self) -> &mut[Square; 4] {
-        &mut self.squares
+    pub fn get_squares_owned(&self) -> Vec<Square>{
+        self.squares.clone()
     }
 
 
-    pub fn get_squares_owned(&self) -> [Square; 4]{
-        self.squares
+    pub fn get_rotation_type(&self) -> RotationType {
+        self.rotation
     }
 
 
-    pub fn get_rotation_type(&self) -> RotationType {
-        self.rotation
+    pub fn get_kicks(&self) -> &[Vector2<isize>] {
+        &self.kicks
     }
 
 
@@ -156,7 +214,7 @@ impl Piece {
     }
 
 
-    pub fn to_initial_rotation(&mut self) {
+    pub fn reset_rotation(&mut self) {
         if self.rotation_position == 0 {
             return;
         }
@@ -177,17 +235,37 @@ impl Piece {
     }
 
 
-    pub fn has_same_type(&self, other: &Piece) -> bool {
-        self.piece_type == other.piece_type
+    // Same squares around the first one, whatever the position and rotation of both pieces
+    pub fn has_shape_of(&self, other: &Piece) -> bool {
+        let get_cells = |piece: &Piece| {
+            let pivot = piece.squares[0].get_position();
+            let mut cells = piece.squares.iter().map(|square| {
+                let cell = square.get_position() - pivot;
+                (cell.x, cell.y)
+            }).collect::<Vec<_>>();
+            cells.sort();
+            cells
+        };
+        let cells = get_cells(self);
+        let mut other = other.clone();
+        (0..4).any(|_| {
+            other.rotate();
+            get_cells(&other) == cells
+        })
     }
 
 
-    pub fn to_array(mut self) -> [[bool; 4]; 2] {
-        let mut piece_holder = [[false; 4]; 2];
-        self.move_at(Vector2::new(1,0));
-        for square in self.squares {
-            let position = square.get_position();
-            piece_holder[position.y as usize][position.x as usize] = true;
+    // Rows of the piece bounding box, the lowest row first
+    pub fn to_array(&self) -> Vec<Vec<bool>> {
+        let positions = self.squares.iter().map(|square| square.get_position()).collect::<Vec<_>>();
+        let min_x = positions.iter().map(|position| position.x).min().unwrap_or(0);
+        let max_x = positions.iter().map(|position| position.x).max().unwrap_or(-1);
+        let min_y = positions.iter().map(|position| position.y).min().unwrap_or(0);
+        let max_y = positions.iter().map(|position| position.y).max().unwrap_or(-1);
+
+        let mut piece_holder = vec![vec![false; (max_x - min_x + 1) as usize]; (max_y - min_y + 1) as usize];
+        for position in positions {
+            piece_holder[(position.y - min_y) as usize][(position.x - min_x) as usize] = true;
         }
         piece_holder
     }
@@ -221,7 +299,7 @@ impl Eq for Piece {}
 
 #[cfg(test)]
 mod test {
-    use kiss3d::nalgebra::Vector2;
+    use nalgebra::Vector2;
 
     use crate::piece_factory::PieceType;
 
@@ -247,12 +325,13 @@ mod test {
             Vector2::new(4, 4),
             Color::Cyan
         );
+        // The rotation center is the corner at (2.5, 1.5)
         let rotation_type = RotationType::AroundPoint(Vector2::new(2, 2));
         let pivot = Vector2::new(2, 2);
 
         let result = sut.get_rotated_position(rotation_type, pivot);
 
-        assert_eq!(result, Vector2::new(3, 0));
+        assert_eq!(result, Vector2::new(5, 0));
     }
 
 
@@ -320,12 +399,13 @@ mod test {
         println!("{:?}", sut.get_squares().iter().map(|s| s.get_position()).collect::<Vec<_>>());
         match sut.rotation {
             RotationType::AroundSquare => println!("{}", sut.get_squares()[0].get_position()),
-            RotationType::AroundPoint(point) => println!("{}", point)
+            RotationType::AroundPoint(point) => println!("{}", point),
+            RotationType::None => ()
         }
         
         let rotation_type = sut.get_rotation_type();
         let pivot = sut.get_squares()[0].get_position(); 
-        let new_positions = sut.get_squares_mut()
+        let new_positions = sut.get_squares()
             .iter()
             .map(|square| square.get_rotated_position(rotation_type, pivot))
             .collect::<Vec<_>>();
@@ -346,7 +426,7 @@ mod test {
 
         let rotation_type = sut.get_rotation_type();
         let pivot = sut.get_squares()[0].get_position(); 
-        let new_positions = sut.get_squares_mut()
+        let new_positions = sut.get_squares()
             .iter()
             .map(|square| square.get_rotated_position(rotation_type, pivot))
             .collect::<Vec<_>>();
@@ -356,4 +436,30 @@ mod test {
         assert_eq!(new_positions[2], Vector2::new(5, 2));
         assert_eq!(new_positions[3], Vector2::new(6, 3));
     }
-}
\ No newline at end of file
+
+
+    #[test]
+    fn array_of_a_piece_covers_its_bounding_box() {
+        let squares = [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (2, 1)].iter()
+            .map(|(x, y)| Square::new(Vector2::new(*x, *y), Color::Gray))
+            .collect::<Vec<_>>();
+        let sut = Piece::new(squares, RotationType::None, vec![], PieceType::T);
+
+        let result = sut.to_array();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0], vec![true; 5]);
+        assert_eq!(result[1], vec![false, false, true, false, false]);
+    }
+
+
+    #[test]
+    fn pieces_have_the_same_shape_whatever_their_rotation() {
+        let mut sut = Piece::from(PieceType::T);
+        sut.rotate();
+        sut.translate(Vector2::new(3, -2));
+
+        assert!(sut.has_shape_of(&Piece::from(PieceType::T)));
+        assert!(!sut.has_shape_of(&Piece::from(PieceType::L)));
+    }
+}
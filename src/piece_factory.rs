@@ -1,225 +1,345 @@
-use kiss3d::nalgebra::Vector2;
+use std::{fmt::Display, fs, path::Path};
 
-use strum_macros::EnumIter;
+use nalgebra::Vector2;
 
 use crate::piece::{Piece, Square, Color, RotationType};
 
 pub const SPAWN_POINT: Vector2<isize> = Vector2::new(100, 100);
 
-#[derive(EnumIter, Copy, Clone, PartialEq, Hash, Debug)]
-pub enum PieceType {
-    T,
-    LeftL,
-    RightL,
-    LeftSkew,
-    RightSkew,
-    Square,
-    Straight
+const TETROMINO_KICKS: [Vector2<isize>; 2] = [Vector2::new(1, 0), Vector2::new(-1, 0)];
+
+// Pieces are told apart by their letter, which is unique within a piece set
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct PieceType(pub char);
+
+impl PieceType {
+    pub const T: PieceType = PieceType('T');
+    pub const J: PieceType = PieceType('J');
+    pub const L: PieceType = PieceType('L');
+    pub const S: PieceType = PieceType('S');
+    pub const Z: PieceType = PieceType('Z');
+    pub const O: PieceType = PieceType('O');
+    pub const I: PieceType = PieceType('I');
+
+
+    // Only the tetrominoes are known without a piece set
+    pub fn from_letter(letter: char) -> Option<PieceType> {
+        PieceFactory::tetrominoes().get_definition(PieceType(letter)).map(|definition| definition.piece_type)
+    }
+}
+
+
+// Cells and rotation point are relative to the spawn point, the first cell
+// being the pivot of an AroundSquare rotation
+#[derive(Clone, PartialEq, Debug)]
+pub struct PieceDefinition {
+    piece_type: PieceType,
+    color: Color,
+    rotation: RotationType,
+    cells: Vec<Vector2<isize>>,
+    kicks: Vec<Vector2<isize>>
+}
+
+impl PieceDefinition {
+
+    pub fn new(piece_type: PieceType, color: Color, rotation: RotationType, cells: Vec<Vector2<isize>>, kicks: Vec<Vector2<isize>>) -> Self {
+        PieceDefinition {
+            piece_type,
+            color,
+            rotation,
+            cells,
+            kicks
+        }
+    }
+
+
+    pub fn create(&self) -> Piece {
+        let squares = self.cells.iter()
+            .map(|cell| Square::of_piece(SPAWN_POINT + cell, self.color, self.piece_type))
+            .collect::<Vec<_>>();
+        let rotation = match self.rotation {
+            RotationType::AroundPoint(point) => RotationType::AroundPoint(SPAWN_POINT + point),
+            rotation => rotation
+        };
+        Piece::new(squares, rotation, self.kicks.clone(), self.piece_type)
+    }
+}
+
+
+#[derive(Debug)]
+pub struct PieceSetError(String);
+
+impl Display for PieceSetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/*
+ * Piece sets are text files of blocks starting with "piece:", '#' starting a comment :
+ *
+ *   piece: T                   (a letter unique in the set)
+ *   color: purple
+ *   rotation: square           (square, point <x> <y> or none)
+ *   kicks: 1 0, -1 0           (optional, offsets tried when the rotation collides)
+ *   cells: 0 0, -1 0, 1 0, 0 1
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct PieceSet {
+    definitions: Vec<PieceDefinition>
+}
+
+impl PieceSet {
+
+    pub fn new(definitions: Vec<PieceDefinition>) -> Result<PieceSet, PieceSetError> {
+        if definitions.is_empty() {
+            return Err(PieceSetError(String::from("A piece set needs at least one piece")));
+        }
+
+        for (index, definition) in definitions.iter().enumerate() {
+            if definition.cells.is_empty() {
+                return Err(PieceSetError(format!("Piece {} has no cell", definition.piece_type.0)));
+            }
+            if definitions[..index].iter().any(|other| other.piece_type == definition.piece_type) {
+                return Err(PieceSetError(format!("Piece {} is defined twice", definition.piece_type.0)));
+            }
+        }
+
+        Ok(PieceSet {
+            definitions
+        })
+    }
+
+
+    pub fn load(path: &Path) -> Result<PieceSet, PieceSetError> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| PieceSetError(format!("Cannot read {} : {}", path.display(), error)))?;
+        PieceSet::parse(&text)
+    }
+
+
+    pub fn parse(text: &str) -> Result<PieceSet, PieceSetError> {
+        let mut definitions = vec![];
+        let mut current: Option<PieceDefinition> = None;
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let (key, value) = line.split_once(':')
+                .ok_or(PieceSetError(format!("Expected \"key: value\", found : {}", line)))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            if key == "piece" {
+                let mut letters = value.chars();
+                let letter = match (letters.next(), letters.next()) {
+                    (Some(letter), None) => letter,
+                    _ => return Err(PieceSetError(format!("A piece is named by one letter, found : {}", value)))
+                };
+                definitions.extend(current.take());
+                current = Some(PieceDefinition::new(PieceType(letter), Color::Gray, RotationType::AroundSquare, vec![], vec![]));
+                continue;
+            }
+
+            let definition = current.as_mut()
+                .ok_or(PieceSetError(format!("{} given before any piece", key)))?;
+            match key {
+                "color" => definition.color = Color::from_name(value)
+                    .ok_or(PieceSetError(format!("Unknown color : {}", value)))?,
+                "rotation" => definition.rotation = PieceSet::parse_rotation(value)?,
+                "kicks" => definition.kicks = PieceSet::parse_vectors(value)?,
+                "cells" => definition.cells = PieceSet::parse_vectors(value)?,
+                _ => return Err(PieceSetError(format!("Unknown key : {}", key)))
+            }
+        }
+        definitions.extend(current);
+
+        PieceSet::new(definitions)
+    }
+
+
+    pub fn to_text(&self) -> String {
+        let vectors = |vectors: &[Vector2<isize>]| vectors.iter()
+            .map(|vector| format!("{} {}", vector.x, vector.y))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut text = String::new();
+        for definition in self.definitions.iter() {
+            let rotation = match definition.rotation {
+                RotationType::AroundSquare => String::from("square"),
+                RotationType::AroundPoint(point) => format!("point {} {}", point.x, point.y),
+                RotationType::None => String::from("none")
+            };
+            text.push_str(&format!("piece: {}\ncolor: {}\nrotation: {}\n", definition.piece_type.0, definition.color.get_name(), rotation));
+            if !definition.kicks.is_empty() {
+                text.push_str(&format!("kicks: {}\n", vectors(&definition.kicks)));
+            }
+            text.push_str(&format!("cells: {}\n", vectors(&definition.cells)));
+        }
+        text
+    }
+
+
+    fn parse_rotation(value: &str) -> Result<RotationType, PieceSetError> {
+        match value.split_whitespace().collect::<Vec<_>>()[..] {
+            ["square"] => Ok(RotationType::AroundSquare),
+            ["none"] => Ok(RotationType::None),
+            ["point", x, y] => Ok(RotationType::AroundPoint(PieceSet::parse_vector(&format!("{} {}", x, y))?)),
+            _ => Err(PieceSetError(format!("Unknown rotation : {}", value)))
+        }
+    }
+
+
+    fn parse_vector(value: &str) -> Result<Vector2<isize>, PieceSetError> {
+        let coordinates = value.split_whitespace()
+            .map(|coordinate| coordinate.parse::<isize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| PieceSetError(format!("Expected two integers, found : {}", value)))?;
+        match coordinates[..] {
+            [x, y] => Ok(Vector2::new(x, y)),
+            _ => Err(PieceSetError(format!("Expected two integers, found : {}", value)))
+        }
+    }
+
+
+    // Comma separated "x y" pairs
+    fn parse_vectors(value: &str) -> Result<Vec<Vector2<isize>>, PieceSetError> {
+        value.split(',').map(PieceSet::parse_vector).collect()
+    }
+
+
+    pub fn get_piece_types(&self) -> Vec<PieceType> {
+        self.definitions.iter().map(|definition| definition.piece_type).collect()
+    }
+
+
+    pub fn get_definition(&self, piece_type: PieceType) -> Option<&PieceDefinition> {
+        self.definitions.iter().find(|definition| definition.piece_type == piece_type)
+    }
+
+
+    pub fn get_piece(&self, piece_type: PieceType) -> Option<Piece> {
+        self.get_definition(piece_type).map(PieceDefinition::create)
+    }
 }
 
+
 pub struct PieceFactory;
 
 impl PieceFactory {
 
     pub fn get_piece(piece_type: PieceType) -> Piece {
-        match piece_type {
-            PieceType::T => Self::get_t_piece(),
-            PieceType::LeftL => Self::get_left_l_piece(),
-            PieceType::RightL => Self::get_right_l_piece(),
-            PieceType::LeftSkew => Self::get_left_skew_piece(),
-            PieceType::RightSkew => Self::get_right_skew_piece(),
-            PieceType::Square => Self::get_square_piece(),
-            PieceType::Straight => Self::get_straight_piece()
-        }
+        PieceFactory::tetrominoes().get_piece(piece_type)
+            .unwrap_or_else(|| panic!("{:?} is not a tetromino", piece_type))
     }
 
-    fn get_t_piece() -> Piece {
-        let squares = [
-            Square::new(
-                SPAWN_POINT,
-                Color::Purple
-            ),
-            Square::new(
-                Vector2::new(SPAWN_POINT.x - 1, SPAWN_POINT.y),
-                Color::Purple
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(1, 0),
-                Color::Purple
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(0, 1),
-                Color::Purple
-            )
-        ];
-
-        Piece::new(
-            squares,
-            RotationType::AroundSquare,
-            PieceType::T
-        )
-    }
-
-    fn get_left_l_piece() -> Piece {
-        let squares = [
-            Square::new(
-                SPAWN_POINT,
-                Color::DarkBlue
-            ),
-            Square::new(
-                Vector2::new(SPAWN_POINT.x - 1, SPAWN_POINT.y),
-                Color::DarkBlue
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(1, 0),
-                Color::DarkBlue
-            ),
-            Square::new(
-                Vector2::new(SPAWN_POINT.x - 1, SPAWN_POINT.y + 1),
-                Color::DarkBlue
-            )
-        ];
-
-        Piece::new(
-            squares,
-            RotationType::AroundSquare,
-            PieceType::LeftL
-        )
-    }
-
-    fn get_right_l_piece() -> Piece {
-        let squares = [
-            Square::new(
-                SPAWN_POINT,
-                Color::Orange
-            ),
-            Square::new(
-                Vector2::new(SPAWN_POINT.x - 1, SPAWN_POINT.y),
-                Color::Orange
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(1, 0),
-                Color::Orange
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(1, 1),
-                Color::Orange
-            )
-        ];
-
-        Piece::new(
-            squares,
-            RotationType::AroundSquare,
-            PieceType::RightL
-        )
-    }
-
-    fn get_left_skew_piece() -> Piece {
-        let squares = [
-            Square::new(
-                SPAWN_POINT,
-                Color::Green
-            ),
-            Square::new(
-                Vector2::new(SPAWN_POINT.x - 1, SPAWN_POINT.y),
-                Color::Green
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(0, 1),
-                Color::Green
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(1, 1),
-                Color::Green
-            )
-        ];
-
-        Piece::new(
-            squares,
-            RotationType::AroundSquare,
-            PieceType::LeftSkew
-        )
-    }
-
-    fn get_right_skew_piece() -> Piece {
-        let squares = [
-            Square::new(
-                SPAWN_POINT,
-                Color::Red
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(1, 0),
-                Color::Red
-            ),
-            Square::new(
-                Vector2::new(SPAWN_POINT.x - 1, SPAWN_POINT.y + 1),
-                Color::Red
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(0, 1),
-                Color::Red
-            )
-        ];
-
-        Piece::new(
-            squares,
-            RotationType::AroundSquare,
-            PieceType::RightSkew
-        )
-    }
-
-    fn get_square_piece() -> Piece {
-        let squares = [
-            Square::new(
-                SPAWN_POINT,
-                Color::Yellow
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(1, 0),
-                Color::Yellow
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(0, 1),
-                Color::Yellow
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(1, 1),
-                Color::Yellow
-            )
-        ];
-
-        Piece::new(
-            squares,
-            RotationType::AroundPoint(SPAWN_POINT),
-            PieceType::Square
-        )
-    }
-
-    fn get_straight_piece() -> Piece {
-        let squares = [
-            Square::new(
-                SPAWN_POINT,
-                Color::Cyan
-            ),
-            Square::new(
-                Vector2::new(SPAWN_POINT.x - 1, SPAWN_POINT.y),
-                Color::Cyan
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(1, 0),
-                Color::Cyan
-            ),
-            Square::new(
-                SPAWN_POINT + Vector2::new(2, 0),
-                Color::Cyan
+
+    pub fn tetrominoes() -> PieceSet {
+        let definition = |piece_type, color, rotation, cells: [(isize, isize); 4]| {
+            PieceDefinition::new(
+                piece_type,
+                color,
+                rotation,
+                cells.iter().map(|(x, y)| Vector2::new(*x, *y)).collect(),
+                TETROMINO_KICKS.to_vec()
             )
-        ];
+        };
+
+        PieceSet {
+            definitions: vec![
+                definition(PieceType::T, Color::Purple, RotationType::AroundSquare, [(0, 0), (-1, 0), (1, 0), (0, 1)]),
+                definition(PieceType::J, Color::DarkBlue, RotationType::AroundSquare, [(0, 0), (-1, 0), (1, 0), (-1, 1)]),
+                definition(PieceType::L, Color::Orange, RotationType::AroundSquare, [(0, 0), (-1, 0), (1, 0), (1, 1)]),
+                definition(PieceType::S, Color::Green, RotationType::AroundSquare, [(0, 0), (-1, 0), (0, 1), (1, 1)]),
+                definition(PieceType::Z, Color::Red, RotationType::AroundSquare, [(0, 0), (1, 0), (-1, 1), (0, 1)]),
+                definition(PieceType::O, Color::Yellow, RotationType::None, [(0, 0), (1, 0), (0, 1), (1, 1)]),
+                definition(PieceType::I, Color::Cyan, RotationType::AroundPoint(Vector2::new(0, 0)), [(0, 0), (-1, 0), (1, 0), (2, 0)])
+            ]
+        }
+    }
+
+
+    pub fn pentominoes() -> PieceSet {
+        PieceSet::parse(include_str!("../pieces/pentominoes.txt"))
+            .expect("The shipped pentomino set is valid")
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use nalgebra::Vector2;
 
-        Piece::new(
-            squares,
-            RotationType::AroundPoint(SPAWN_POINT),
-            PieceType::Straight
-        )
+    use crate::piece::RotationType;
+
+    use super::{PieceSet, PieceType, PieceFactory, SPAWN_POINT};
+
+    #[test]
+    fn shipped_tromino_set_is_valid() {
+        let sut = PieceSet::parse(include_str!("../pieces/trominoes.txt")).unwrap();
+
+        let piece = sut.get_piece(PieceType('V')).unwrap();
+
+        assert_eq!(sut.get_piece_types().len(), 2);
+        assert_eq!(piece.get_squares().len(), 3);
+        assert_eq!(piece.get_squares()[0].get_position(), SPAWN_POINT);
+    }
+
+
+    #[test]
+    fn pieces_are_built_from_their_definition() {
+        let text = "piece: P\ncolor: red\nrotation: point 0 1\nkicks: 1 0\ncells: 0 0, 1 0, 0 1, 1 1, 0 2";
+        let sut = PieceSet::parse(text).unwrap();
+
+        let piece = sut.get_piece(PieceType('P')).unwrap();
+
+        assert_eq!(piece.get_squares().len(), 5);
+        assert_eq!(piece.get_kicks(), &[Vector2::new(1, 0)]);
+        assert_eq!(piece.get_rotation_type(), RotationType::AroundPoint(SPAWN_POINT + Vector2::new(0, 1)));
+    }
+
+
+    #[test]
+    fn piece_set_text_round_trips() {
+        let sut = PieceFactory::pentominoes();
+
+        assert_eq!(PieceSet::parse(&sut.to_text()).unwrap(), sut);
+    }
+
+
+    #[test]
+    fn duplicated_letters_are_rejected() {
+        let text = "piece: A\ncells: 0 0\npiece: A\ncells: 0 0, 1 0";
+
+        assert!(PieceSet::parse(text).is_err());
+    }
+
+
+    #[test]
+    fn pentominoes_are_18_distinct_one_sided_pieces() {
+        let sut = PieceFactory::pentominoes();
+        let mut shapes = vec![];
+
+        for piece_type in sut.get_piece_types() {
+            let mut piece = sut.get_piece(piece_type).unwrap();
+            assert_eq!(piece.get_squares().len(), 5);
+
+            // Every orientation of a piece must differ from all the other pieces
+            let mut orientations = vec![];
+            for _ in 0..4 {
+                orientations.push(piece.to_array());
+                piece.rotate();
+            }
+            assert!(shapes.iter().all(|shape| !orientations.contains(shape)));
+            shapes.extend(orientations);
+        }
+
+        assert_eq!(sut.get_piece_types().len(), 18);
     }
 
-}
\ No newline at end of file
+
+    #[test]
+    fn tetrominoes_are_found_back_from_their_letter() {
+        for piece_type in PieceFactory::tetrominoes().get_piece_types() {
+            assert_eq!(PieceType::from_letter(piece_type.0), Some(piece_type));
+        }
+        assert!(PieceType::from_letter('W').is_none());
+    }
+}
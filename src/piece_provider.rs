@@ -1,8 +1,9 @@
+use std::collections::VecDeque;
+
 use rand_chacha::ChaCha8Rng;
-use strum::IntoEnumIterator;
 use rand::prelude::SliceRandom;
 
-use crate::{piece::Piece, piece_factory::PieceType};
+use crate::{piece::Piece, piece_factory::{PieceType, PieceSet, PieceFactory}};
 
 struct PieceBag {
     pieces: Vec<Piece>,
@@ -10,11 +11,11 @@ struct PieceBag {
 
 impl PieceBag {
 
-    fn new(rng: &mut ChaCha8Rng) -> Self {
-        let mut pieces = PieceType::iter().collect::<Vec<_>>();
+    fn new(piece_set: &PieceSet, rng: &mut ChaCha8Rng) -> Self {
+        let mut pieces = piece_set.get_piece_types();
         pieces.shuffle(rng);
         PieceBag {
-            pieces: pieces.iter().map(|piece_type| Piece::from(*piece_type)).collect::<Vec<_>>()
+            pieces: pieces.iter().filter_map(|piece_type| piece_set.get_piece(*piece_type)).collect::<Vec<_>>()
         }
     }
 
@@ -27,35 +28,66 @@ impl PieceBag {
     }
 }
 
+enum PieceSource {
+    Bag {
+        piece_set: PieceSet,
+        current_bag: PieceBag,
+        rng: Box<ChaCha8Rng>
+    },
+    // Puzzles play a fixed queue of pieces, nothing comes after it
+    Sequence(VecDeque<PieceType>)
+}
+
 pub struct PieceProvider {
-    current_bag: PieceBag,
-    rng: ChaCha8Rng
+    source: PieceSource
 }
 
 impl PieceProvider {
 
-    pub fn new(mut rng: ChaCha8Rng) -> Self {
+    pub fn new(rng: ChaCha8Rng) -> Self {
+        PieceProvider::with_piece_set(PieceFactory::tetrominoes(), rng)
+    }
+
+
+    pub fn with_piece_set(piece_set: PieceSet, mut rng: ChaCha8Rng) -> Self {
         PieceProvider {
-            current_bag: PieceBag::new(&mut rng),
-            rng
+            source: PieceSource::Bag {
+                current_bag: PieceBag::new(&piece_set, &mut rng),
+                piece_set,
+                rng: Box::new(rng)
+            }
         }
     }
 
-    pub fn get_piece(&mut self) -> Piece {
-        if self.current_bag.get_bag_size() == 0 {
-            self.current_bag = PieceBag::new(&mut self.rng);
+
+    pub fn from_sequence(pieces: Vec<PieceType>) -> Self {
+        PieceProvider {
+            source: PieceSource::Sequence(pieces.into())
+        }
+    }
+
+
+    pub fn get_piece(&mut self) -> Option<Piece> {
+        match &mut self.source {
+            PieceSource::Bag { piece_set, current_bag, rng } => {
+                if current_bag.get_bag_size() == 0 {
+                    *current_bag = PieceBag::new(piece_set, rng);
+                }
+
+                current_bag.get_piece()
+            },
+            PieceSource::Sequence(pieces) => pieces.pop_front().map(Piece::from)
         }
-        
-        self.current_bag.get_piece().unwrap()
     }
 }
 
 
+#[cfg(test)]
 mod test {
 
     use rand::SeedableRng;
     use rand_chacha::ChaCha8Rng;
-    use crate::piece_factory::PieceType;
+    use crate::{piece::Piece, piece_factory::{PieceType, PieceFactory}};
 
     use super::{PieceProvider, PieceBag};
     
@@ -92,7 +124,7 @@ mod test {
     #[test]
     fn every_piece_in_a_bag_is_different() {
         let mut rng = ChaCha8Rng::seed_from_u64(1);
-        let mut bag = PieceBag::new(&mut rng);
+        let mut bag = PieceBag::new(&PieceFactory::tetrominoes(), &mut rng);
         let mut pieces = vec![];
         while let Some(piece) = bag.get_piece(){
             pieces.push(piece);
@@ -102,4 +134,14 @@ mod test {
 
         assert!(result);
     }
-}
\ No newline at end of file
+
+
+    #[test]
+    fn sequence_provider_stops_after_its_last_piece() {
+        let mut provider = PieceProvider::from_sequence(vec![PieceType::T, PieceType::I]);
+
+        assert!(provider.get_piece() == Some(Piece::from(PieceType::T)));
+        assert!(provider.get_piece() == Some(Piece::from(PieceType::I)));
+        assert!(provider.get_piece().is_none());
+    }
+}
@@ -0,0 +1,364 @@
+use std::{fmt::Display, fs, path::Path};
+
+use nalgebra::Vector2;
+
+use crate::{
+    gameboard::{GameBoard, BoardRules, HoldRule, BOARD_WIDTH, BOARD_HEIGHT},
+    game_mode::GameMode,
+    piece::{Square, Color, Piece},
+    piece_factory::{PieceType, PieceFactory},
+    piece_provider::PieceProvider,
+    scoring::ScoringKind
+};
+
+/*
+ * Puzzles are text files made of "key: value" lines, '#' starting a comment :
+ *
+ *   goal: lines 2          (lines <count>, perfect-clear or tspin-double)
+ *   hold: no               (yes or no, yes by default)
+ *   queue: T I O           (the only pieces available, in order)
+ *   board:
+ *   ...X......
+ *   XXX...XXXX
+ *
+ * The rows following "board:" are given from top to bottom and end on the floor.
+ * '.' is an empty cell, 'X' a garbage cell and a piece letter a cell of its color.
+ */
+
+const EMPTY_CELL: char = '.';
+const GARBAGE_CELL: char = 'X';
+
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+pub enum PuzzleGoal {
+    Lines(u32),
+    #[default]
+    PerfectClear,
+    TSpinDouble
+}
+
+impl PuzzleGoal {
+
+    fn from_text(text: &str) -> Option<PuzzleGoal> {
+        match text.split_whitespace().collect::<Vec<_>>()[..] {
+            ["lines", count] => count.parse::<u32>().ok().map(PuzzleGoal::Lines),
+            ["perfect-clear"] => Some(PuzzleGoal::PerfectClear),
+            ["tspin-double"] => Some(PuzzleGoal::TSpinDouble),
+            _ => None
+        }
+    }
+
+
+    fn to_text(self) -> String {
+        match self {
+            PuzzleGoal::Lines(count) => format!("lines {}", count),
+            PuzzleGoal::PerfectClear => String::from("perfect-clear"),
+            PuzzleGoal::TSpinDouble => String::from("tspin-double")
+        }
+    }
+
+
+    fn is_reached(&self, board: &GameBoard) -> bool {
+        let last_clear = board.get_last_clear();
+        match self {
+            PuzzleGoal::Lines(count) => board.get_lines_cleared() >= *count,
+            PuzzleGoal::PerfectClear => last_clear.is_some_and(|clear| clear.perfect_clear),
+            PuzzleGoal::TSpinDouble => last_clear.is_some_and(|clear| clear.t_spin && clear.lines == 2)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PuzzleError(String);
+
+impl Display for PuzzleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct Puzzle {
+    // Bottom row first
+    rows: Vec<Vec<Option<Color>>>,
+    queue: Vec<PieceType>,
+    hold: bool,
+    goal: PuzzleGoal
+}
+
+impl Puzzle {
+
+    pub fn load(path: &Path) -> Result<Puzzle, PuzzleError> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| PuzzleError(format!("Cannot read {} : {}", path.display(), error)))?;
+        Puzzle::parse(&text)
+    }
+
+
+    pub fn parse(text: &str) -> Result<Puzzle, PuzzleError> {
+        let mut rows = vec![];
+        let mut queue = None;
+        let mut hold = true;
+        let mut goal = None;
+        let mut in_board = false;
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            if in_board {
+                rows.push(Puzzle::parse_row(line)?);
+                continue;
+            }
+
+            let (key, value) = line.split_once(':')
+                .ok_or(PuzzleError(format!("Expected \"key: value\", found : {}", line)))?;
+            let value = value.trim();
+            match key.trim() {
+                "goal" => goal = Some(PuzzleGoal::from_text(value)
+                    .ok_or(PuzzleError(format!("Unknown goal : {}", value)))?),
+                "hold" => hold = match value {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return Err(PuzzleError(format!("Hold is either yes or no, found : {}", value)))
+                },
+                "queue" => queue = Some(value.split_whitespace().map(|name| {
+                    let mut letters = name.chars();
+                    match (letters.next().and_then(PieceType::from_letter), letters.next()) {
+                        (Some(piece_type), None) => Ok(piece_type),
+                        _ => Err(PuzzleError(format!("Unknown piece : {}", name)))
+                    }
+                }).collect::<Result<Vec<_>, _>>()?),
+                "board" => in_board = true,
+                _ => return Err(PuzzleError(format!("Unknown key : {}", key)))
+            }
+        }
+
+        if rows.len() > BOARD_HEIGHT - 2 {
+            return Err(PuzzleError(format!("A puzzle board has at most {} rows", BOARD_HEIGHT - 2)));
+        }
+        rows.reverse();
+
+        Ok(Puzzle {
+            rows,
+            queue: queue.ok_or(PuzzleError(String::from("Missing piece queue")))?,
+            hold,
+            goal: goal.ok_or(PuzzleError(String::from("Missing goal")))?
+        })
+    }
+
+
+    fn parse_row(line: &str) -> Result<Vec<Option<Color>>, PuzzleError> {
+        let row = line.chars().map(|cell| match cell {
+            EMPTY_CELL => Ok(None),
+            GARBAGE_CELL => Ok(Some(Color::Gray)),
+            letter => PieceType::from_letter(letter)
+                .map(|piece_type| Some(Piece::from(piece_type).get_squares()[0].get_color()))
+                .ok_or(PuzzleError(format!("Unknown cell : {}", letter)))
+        }).collect::<Result<Vec<_>, _>>()?;
+
+        match row.len() == BOARD_WIDTH {
+            true => Ok(row),
+            false => Err(PuzzleError(format!("Rows are {} cells wide, found : {}", BOARD_WIDTH, line)))
+        }
+    }
+
+
+    pub fn to_text(&self) -> String {
+        let queue = self.queue.iter().map(|piece_type| piece_type.0.to_string()).collect::<Vec<_>>().join(" ");
+        let hold = match self.hold {
+            true => "yes",
+            false => "no"
+        };
+        let mut text = format!("goal: {}\nhold: {}\nqueue: {}\nboard:\n", self.goal.to_text(), hold, queue);
+        for row in self.rows.iter().rev() {
+            text.extend(row.iter().map(|cell| Puzzle::get_cell_letter(*cell)));
+            text.push('\n');
+        }
+        text
+    }
+
+
+    // Pieces are told apart by their color on the board
+    fn get_cell_letter(cell: Option<Color>) -> char {
+        match cell {
+            None => EMPTY_CELL,
+            Some(Color::Gray) => GARBAGE_CELL,
+            Some(color) => PieceFactory::tetrominoes().get_piece_types().into_iter()
+                .find(|piece_type| Piece::from(*piece_type).get_squares()[0].get_color() == color)
+                .map_or(GARBAGE_CELL, |piece_type| piece_type.0)
+        }
+    }
+
+
+    pub fn setup(&self, board: &mut GameBoard) {
+        for (y, row) in self.rows.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if let Some(color) = cell {
+                    board.place_square(Square::new(Vector2::new(x as isize, y as isize), *color));
+                }
+            }
+        }
+        board.set_piece_provider(PieceProvider::from_sequence(self.queue.clone()));
+    }
+}
+
+
+// Solved as soon as the goal is reached, failed when the queue runs out before
+pub struct PuzzleMode {
+    puzzle: Puzzle,
+    pieces_placed: u32,
+    solved: bool
+}
+
+impl PuzzleMode {
+
+    pub fn new(puzzle: Puzzle) -> Self {
+        PuzzleMode {
+            puzzle,
+            pieces_placed: 0,
+            solved: false
+        }
+    }
+}
+
+impl GameMode for PuzzleMode {
+
+    fn get_rules(&self) -> BoardRules {
+        BoardRules {
+            hold: match self.puzzle.hold {
+                true => HoldRule::OncePerPiece,
+                false => HoldRule::Disabled
+            },
+            ..BoardRules::default()
+        }
+    }
+
+
+    fn get_scoring(&self) -> ScoringKind {
+        ScoringKind::Guideline
+    }
+
+
+    fn get_gravity(&self) -> Option<f32> {
+        None
+    }
+
+
+    fn get_lock_delay(&self) -> Option<f32> {
+        None
+    }
+
+
+    fn prepare(&self, board: &mut GameBoard) {
+        self.puzzle.setup(board);
+    }
+
+
+    fn update(&mut self, board: &mut GameBoard, _time_delta: f32) {
+        if board.get_pieces_placed() != self.pieces_placed {
+            self.pieces_placed = board.get_pieces_placed();
+            self.solved |= self.puzzle.goal.is_reached(board);
+        }
+    }
+
+
+    fn is_finished(&self) -> bool {
+        self.solved
+    }
+
+
+    fn get_result(&self) -> Option<String> {
+        match self.solved {
+            true => Some(String::from("Puzzle solved !")),
+            false => Some(String::from("Puzzle failed"))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{gameboard::{GameBoard, MovementDirection}, game_mode::GameMode};
+
+    use super::{Puzzle, PuzzleGoal, PuzzleMode};
+
+    fn board_for(mode: &PuzzleMode) -> GameBoard {
+        let mut board = GameBoard::new(ChaCha8Rng::seed_from_u64(1), 1);
+        board.set_rules(mode.get_rules());
+        mode.prepare(&mut board);
+        board
+    }
+
+    #[test]
+    fn shipped_puzzles_are_valid() {
+        let t_spin_double = Puzzle::parse(include_str!("../puzzles/t_spin_double.txt")).unwrap();
+        let perfect_clear = Puzzle::parse(include_str!("../puzzles/perfect_clear.txt")).unwrap();
+
+        assert_eq!(t_spin_double.goal, PuzzleGoal::TSpinDouble);
+        assert_eq!(perfect_clear.goal, PuzzleGoal::PerfectClear);
+    }
+
+
+    #[test]
+    fn puzzle_text_round_trips() {
+        let sut = Puzzle::parse(include_str!("../puzzles/t_spin_double.txt")).unwrap();
+
+        assert_eq!(Puzzle::parse(&sut.to_text()).unwrap(), sut);
+    }
+
+
+    #[test]
+    fn rows_of_the_wrong_width_are_rejected() {
+        let result = Puzzle::parse("goal: lines 1\nqueue: T\nboard:\nXXXX\n");
+
+        assert!(result.is_err());
+    }
+
+
+    #[test]
+    fn t_spin_double_puzzle_is_solved_by_a_t_spin() {
+        let puzzle = Puzzle::parse(include_str!("../puzzles/t_spin_double.txt")).unwrap();
+        let mut sut = PuzzleMode::new(puzzle);
+        let mut board = board_for(&sut);
+
+        assert!(board.try_rotate().is_ok());
+        while board.try_fall().is_ok() {}
+        assert!(board.try_rotate().is_ok());
+        board.lock_current_piece();
+        sut.update(&mut board, 0.0);
+
+        assert!(sut.is_finished());
+    }
+
+
+    #[test]
+    fn puzzle_fails_when_the_queue_runs_out() {
+        let puzzle = Puzzle::parse(include_str!("../puzzles/t_spin_double.txt")).unwrap();
+        let mut sut = PuzzleMode::new(puzzle);
+        let mut board = board_for(&sut);
+
+        let _ = board.try_move(MovementDirection::Top);
+        board.lock_current_piece();
+        sut.update(&mut board, 0.0);
+
+        assert!(!board.keep_playing());
+        assert!(!sut.is_finished());
+    }
+
+
+    #[test]
+    fn perfect_clear_puzzle_is_solved_by_emptying_the_board() {
+        let puzzle = Puzzle::parse(include_str!("../puzzles/perfect_clear.txt")).unwrap();
+        let mut sut = PuzzleMode::new(puzzle);
+        let mut board = board_for(&sut);
+
+        for _ in 0..2 {
+            assert!(board.try_move(MovementDirection::Right).is_ok());
+            let _ = board.try_move(MovementDirection::Top);
+            board.lock_current_piece();
+            sut.update(&mut board, 0.0);
+        }
+
+        assert!(sut.is_finished());
+    }
+}
@@ -0,0 +1,316 @@
+use std::{fmt::Display, fs, path::Path};
+
+use crate::{gameboard::{Action, MovementDirection, BlockVisibility}, game_mode::{ModeKind, ModeOptions}, cli::Options, game::{Game, STEP_TIME}, scoring::ScoringKind, puzzle_mode::Puzzle, piece_factory::PieceSet};
+
+#[derive(Debug)]
+pub struct ReplayError(String);
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/*
+ * Replays are text files, a header then one input per line, "#" starting a comment :
+ *
+ *   seed: 1234
+ *   mode: marathon
+ *   infinite-hold: no
+ *   blocks: fading:5          (as given to --blocks)
+ *   scoring: nes              (optional, the scoring of the mode by default)
+ *   pieces:                   (optional, the piece set file indented below its key)
+ *     piece: V
+ *     cells: 0 0, 1 0, 0 1
+ *   frames: 2400              (length of the game in steps)
+ *   12 left                   (frame the input is applied before, then the input)
+ *   40 hard-drop
+ *
+ * A puzzle is written the same way as the piece set, below a "puzzle:" key.
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct Replay {
+    seed: u64,
+    mode: ModeKind,
+    mode_options: ModeOptions,
+    frames: u32,
+    inputs: Vec<(u32, Action)>
+}
+
+fn get_action_name(action: Action) -> &'static str {
+    match action {
+        Action::Move(MovementDirection::Left) => "left",
+        Action::Move(MovementDirection::Right) => "right",
+        Action::Move(MovementDirection::Bottom) => "soft-drop",
+        Action::Move(MovementDirection::Top) => "hard-drop",
+        Action::Rotate => "rotate",
+        Action::Hold => "hold"
+    }
+}
+
+
+fn parse_action(name: &str) -> Option<Action> {
+    match name {
+        "left" => Some(Action::Move(MovementDirection::Left)),
+        "right" => Some(Action::Move(MovementDirection::Right)),
+        "soft-drop" => Some(Action::Move(MovementDirection::Bottom)),
+        "hard-drop" => Some(Action::Move(MovementDirection::Top)),
+        "rotate" => Some(Action::Rotate),
+        "hold" => Some(Action::Hold),
+        _ => None
+    }
+}
+
+impl Replay {
+
+    pub fn new(seed: u64, mode: ModeKind, mode_options: ModeOptions) -> Self {
+        Replay {
+            seed,
+            mode,
+            mode_options,
+            frames: 0,
+            inputs: vec![]
+        }
+    }
+
+
+    pub fn record(&mut self, frame: u32, action: Action) {
+        self.inputs.push((frame, action));
+    }
+
+
+    pub fn set_frames(&mut self, value: u32) {
+        self.frames = value;
+    }
+
+
+    pub fn load(path: &Path) -> Result<Replay, ReplayError> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| ReplayError(format!("Cannot read {} : {}", path.display(), error)))?;
+        Replay::parse(&text)
+    }
+
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        fs::write(path, self.to_text())
+            .map_err(|error| ReplayError(format!("Cannot write {} : {}", path.display(), error)))
+    }
+
+
+    pub fn parse(text: &str) -> Result<Replay, ReplayError> {
+        let mut seed = None;
+        let mut mode = None;
+        let mut mode_options = ModeOptions::default();
+        let mut frames = None;
+        let mut inputs = vec![];
+        // Key and text of the file being read below it
+        let mut block: Option<(&str, String)> = None;
+
+        for line in text.lines() {
+            if let Some((_, block_text)) = block.as_mut() {
+                if line.starts_with(char::is_whitespace) {
+                    block_text.push_str(line.trim());
+                    block_text.push('\n');
+                    continue;
+                }
+            }
+            if let Some((key, block_text)) = block.take() {
+                Replay::parse_block(key, &block_text, &mut mode_options)?;
+            }
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                let value = value.trim();
+                match key.trim() {
+                    "seed" => seed = value.parse::<u64>().ok(),
+                    "mode" => mode = ModeKind::from_name(value),
+                    "frames" => frames = value.parse::<u32>().ok(),
+                    "infinite-hold" => mode_options.infinite_hold = match value {
+                        "yes" => true,
+                        "no" => false,
+                        _ => return Err(ReplayError(format!("Infinite hold is either yes or no, found : {}", value)))
+                    },
+                    "blocks" => mode_options.visibility = BlockVisibility::from_name(value)
+                        .ok_or(ReplayError(format!("Unknown block visibility : {}", value)))?,
+                    "scoring" => mode_options.scoring = Some(ScoringKind::from_name(value)
+                        .ok_or(ReplayError(format!("Unknown scoring : {}", value)))?),
+                    key @ ("puzzle" | "pieces") => block = Some((key, String::new())),
+                    _ => return Err(ReplayError(format!("Unknown key : {}", key)))
+                }
+                continue;
+            }
+
+            let input = line.split_once(' ').and_then(|(frame, action)| {
+                Some((frame.parse::<u32>().ok()?, parse_action(action.trim())?))
+            });
+            inputs.push(input.ok_or(ReplayError(format!("Expected \"<frame> <input>\", found : {}", line)))?);
+        }
+        if let Some((key, block_text)) = block {
+            Replay::parse_block(key, &block_text, &mut mode_options)?;
+        }
+
+        Ok(Replay {
+            seed: seed.ok_or(ReplayError(String::from("Missing or invalid seed")))?,
+            mode: mode.ok_or(ReplayError(String::from("Missing or unknown mode")))?,
+            mode_options,
+            frames: frames.ok_or(ReplayError(String::from("Missing or invalid frame count")))?,
+            inputs
+        })
+    }
+
+
+    fn parse_block(key: &str, text: &str, mode_options: &mut ModeOptions) -> Result<(), ReplayError> {
+        match key {
+            "puzzle" => mode_options.puzzle = Some(Puzzle::parse(text)
+                .map_err(|error| ReplayError(format!("Invalid puzzle : {}", error)))?),
+            _ => mode_options.piece_set = Some(PieceSet::parse(text)
+                .map_err(|error| ReplayError(format!("Invalid piece set : {}", error)))?)
+        }
+        Ok(())
+    }
+
+
+    pub fn to_text(&self) -> String {
+        let hold = match self.mode_options.infinite_hold {
+            true => "yes",
+            false => "no"
+        };
+        let mut text = format!("seed: {}\nmode: {}\ninfinite-hold: {}\nblocks: {}\n",
+            self.seed, self.mode.get_name(), hold, self.mode_options.visibility.get_name());
+        if let Some(scoring) = self.mode_options.scoring {
+            text.push_str(&format!("scoring: {}\n", scoring.get_name()));
+        }
+        let blocks = [
+            ("puzzle", self.mode_options.puzzle.as_ref().map(Puzzle::to_text)),
+            ("pieces", self.mode_options.piece_set.as_ref().map(PieceSet::to_text))
+        ];
+        for (key, block_text) in blocks {
+            if let Some(block_text) = block_text {
+                text.push_str(&format!("{}:\n", key));
+                for line in block_text.lines() {
+                    text.push_str(&format!("  {}\n", line));
+                }
+            }
+        }
+        text.push_str(&format!("frames: {}\n", self.frames));
+        for (frame, action) in self.inputs.iter() {
+            text.push_str(&format!("{} {}\n", frame, get_action_name(*action)));
+        }
+        text
+    }
+
+
+    // Game as it was once the given frame was played, the whole game by default
+    pub fn play(&self, until_frame: Option<u32>) -> Game {
+        self.play_with(until_frame, |_| ())
+    }
+
+
+    // Same as play, the game being shown to on_frame before the first step and after every other
+    pub fn play_with<F: FnMut(&Game)>(&self, until_frame: Option<u32>, mut on_frame: F) -> Game {
+        let options = Options { mode: self.mode, mode_options: self.mode_options.clone(), ..Options::default() };
+        let mut game = Game::new(&options, self.seed);
+        let last_frame = u32::min(until_frame.unwrap_or(self.frames), self.frames);
+        let mut inputs = self.inputs.iter().peekable();
+
+        on_frame(&game);
+        while game.get_frame() < last_frame && !game.is_over() {
+            while let Some((_, action)) = inputs.next_if(|(frame, _)| *frame <= game.get_frame()) {
+                game.apply(*action);
+            }
+            game.step(STEP_TIME);
+            // Replays are not animated
+            game.drain_events();
+            on_frame(&game);
+        }
+        game
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{gameboard::{Action, MovementDirection, BlockVisibility}, game_mode::{ModeKind, ModeOptions}, cli::Options, game::{Game, STEP_TIME}, scoring::ScoringKind, puzzle_mode::Puzzle, piece_factory::PieceSet};
+
+    use super::Replay;
+
+    fn play_recorded_game(options: &Options) -> Game {
+        let mut game = Game::new(options, 7);
+        let inputs = [Action::Move(MovementDirection::Left), Action::Rotate, Action::Move(MovementDirection::Top), Action::Hold];
+        for frame in 0..600 {
+            if frame % 50 == 0 {
+                game.apply(inputs[frame / 50 % inputs.len()]);
+            }
+            game.step(STEP_TIME);
+        }
+        game
+    }
+
+    #[test]
+    fn replay_text_round_trips() {
+        let mode_options = ModeOptions {
+            infinite_hold: true,
+            visibility: BlockVisibility::Fading(2.5),
+            puzzle: Some(Puzzle::parse(include_str!("../puzzles/t_spin_double.txt")).unwrap()),
+            piece_set: Some(PieceSet::parse(include_str!("../pieces/trominoes.txt")).unwrap()),
+            scoring: Some(ScoringKind::Bps)
+        };
+        let mut sut = Replay::new(42, ModeKind::Zen, mode_options);
+        sut.record(3, Action::Rotate);
+        sut.record(3, Action::Move(MovementDirection::Top));
+        sut.set_frames(10);
+
+        assert_eq!(Replay::parse(&sut.to_text()).unwrap(), sut);
+        assert!(Replay::parse("seed: 1\nmode: zen\nframes: 1\n3 jump").is_err());
+    }
+
+
+    #[test]
+    fn replaying_the_inputs_gives_back_the_same_game() {
+        let game = play_recorded_game(&Options::default());
+        let mut replay = game.get_replay().clone();
+        replay.set_frames(game.get_frame());
+
+        let sut = replay.play(None);
+
+        assert_eq!(sut.get_frame(), game.get_frame());
+        assert_eq!(sut.get_board().get_score(), game.get_board().get_score());
+        assert_eq!(sut.get_board().get_pieces_placed(), game.get_board().get_pieces_placed());
+        assert_eq!(format!("{:?}", sut.get_board()), format!("{:?}", game.get_board()));
+    }
+
+
+    #[test]
+    fn replays_keep_the_options_of_the_mode() {
+        let mode_options = ModeOptions {
+            visibility: BlockVisibility::Invisible,
+            piece_set: Some(PieceSet::parse(include_str!("../pieces/trominoes.txt")).unwrap()),
+            scoring: Some(ScoringKind::Sega),
+            ..ModeOptions::default()
+        };
+        let game = play_recorded_game(&Options { mode: ModeKind::Master, mode_options, ..Options::default() });
+        let mut replay = game.get_replay().clone();
+        replay.set_frames(game.get_frame());
+
+        let sut = Replay::parse(&replay.to_text()).unwrap().play(None);
+
+        assert_eq!(sut.get_board().get_score(), game.get_board().get_score());
+        assert_eq!(format!("{:?}", sut.get_board()), format!("{:?}", game.get_board()));
+    }
+
+
+    #[test]
+    fn replays_can_stop_at_any_frame() {
+        let game = play_recorded_game(&Options::default());
+        let mut replay = game.get_replay().clone();
+        replay.set_frames(game.get_frame());
+
+        let sut = replay.play(Some(120));
+
+        assert_eq!(sut.get_frame(), 120);
+        assert!(sut.get_board().get_pieces_placed() < game.get_board().get_pieces_placed());
+    }
+}
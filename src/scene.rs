@@ -0,0 +1,160 @@
+use nalgebra::Vector3;
+
+use crate::{gameboard::GameBoard, piece::{Color, Piece}};
+
+pub const WALL_COLOR: [f32; 3] = [0.35, 0.35, 0.4];
+// Ghost cubes are drawn smaller and darker than the piece
+pub const GHOST_SCALE: f32 = 0.5;
+const GHOST_DIMMING: f32 = 0.35;
+// Side panels are this many cells away from the walls
+const PANEL_MARGIN: f32 = 2.0;
+const PANEL_PIECE_SPACING: f32 = 1.0;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SceneBlock {
+    pub position: Vector3<f32>,
+    pub size: f32,
+    pub color: [f32; 3]
+}
+
+pub fn rgb_of(color: Color) -> [f32; 3] {
+    match color {
+        Color::Cyan => [0.0, 0.9, 0.9],
+        Color::DarkBlue => [0.1, 0.2, 0.9],
+        Color::Orange => [1.0, 0.5, 0.0],
+        Color::Yellow => [0.95, 0.9, 0.0],
+        Color::Green => [0.1, 0.85, 0.1],
+        Color::Purple => [0.6, 0.1, 0.8],
+        Color::Red => [0.9, 0.1, 0.1],
+        Color::Gray => [0.6, 0.6, 0.6]
+    }
+}
+
+
+// Blocks of a piece drawn outside the well, its bounding box starting at the corner
+fn panel_blocks(piece: &Piece, corner: Vector3<f32>, size: f32) -> Vec<SceneBlock> {
+    let color = rgb_of(piece.get_squares()[0].get_color());
+    let mut blocks = vec![];
+    for (y, line) in piece.to_array().iter().enumerate() {
+        for (x, filled) in line.iter().enumerate() {
+            if *filled {
+                blocks.push(SceneBlock {
+                    position: corner + Vector3::new(x as f32, y as f32, 0.0) * size,
+                    size,
+                    color
+                });
+            }
+        }
+    }
+    blocks
+}
+
+
+// Everything drawn for a board, one unit being one drawn cell and the well
+// starting at the origin. Blocks are centered on their position
+pub fn build_scene(board: &GameBoard) -> Vec<SceneBlock> {
+    let dimensions = board.get_dimensions();
+    let size = dimensions.cell_size as f32;
+    let at = |x: isize, y: isize| Vector3::new(x as f32, y as f32, 0.0) * size;
+    let mut blocks = vec![];
+
+    // Walls and floor around the well
+    for y in -1..dimensions.height as isize {
+        blocks.push(SceneBlock { position: at(-1, y), size, color: WALL_COLOR });
+        blocks.push(SceneBlock { position: at(dimensions.width as isize, y), size, color: WALL_COLOR });
+    }
+    for x in 0..dimensions.width as isize {
+        blocks.push(SceneBlock { position: at(x, -1), size, color: WALL_COLOR });
+    }
+
+    for column in board.get_square_board() {
+        for square in column.iter().flatten().filter(|square| board.is_square_shown(square)) {
+            let position = square.get_position();
+            blocks.push(SceneBlock { position: at(position.x, position.y), size, color: rgb_of(square.get_color()) });
+        }
+    }
+
+    for square in board.get_ghost_piece().get_squares() {
+        let position = square.get_position();
+        let color = rgb_of(square.get_color()).map(|component| component * GHOST_DIMMING);
+        blocks.push(SceneBlock { position: at(position.x, position.y), size: size * GHOST_SCALE, color });
+    }
+    for square in board.get_current_piece().get_squares() {
+        let position = square.get_position();
+        blocks.push(SceneBlock { position: at(position.x, position.y), size, color: rgb_of(square.get_color()) });
+    }
+
+    // Hold on the left of the well, next queue on its right going down
+    let top = (dimensions.height as f32 - PANEL_MARGIN) * size;
+    if let Some(held) = board.get_held_piece() {
+        let width = held.to_array()[0].len() as f32;
+        blocks.extend(panel_blocks(held, Vector3::new(-(PANEL_MARGIN + 1.0 + width) * size, top - size, 0.0), size));
+    }
+    let mut corner = Vector3::new((dimensions.width as f32 + PANEL_MARGIN) * size, top, 0.0);
+    for piece in board.get_next_pieces() {
+        let height = piece.to_array().len() as f32;
+        corner.y -= height * size;
+        blocks.extend(panel_blocks(piece, corner, size));
+        corner.y -= PANEL_PIECE_SPACING * size;
+    }
+    blocks
+}
+
+
+#[cfg(test)]
+mod test {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::gameboard::{GameBoard, MovementDirection};
+
+    use super::{build_scene, rgb_of, GHOST_SCALE, WALL_COLOR};
+
+    #[test]
+    fn scene_has_walls_piece_ghost_and_next_queue() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let board = GameBoard::new(rng, 1);
+        let dimensions = board.get_dimensions();
+
+        let sut = build_scene(&board);
+
+        let walls = sut.iter().filter(|block| block.color == WALL_COLOR).count();
+        let ghosts = sut.iter().filter(|block| block.size == GHOST_SCALE).count();
+        let queued_squares = board.get_next_pieces().iter().map(|piece| piece.get_squares().len()).sum::<usize>();
+        assert_eq!(walls, 2 * (dimensions.height + 1) + dimensions.width);
+        assert_eq!(ghosts, 4);
+        assert_eq!(sut.len(), walls + ghosts + 4 + queued_squares);
+    }
+
+
+    #[test]
+    fn ghost_lies_on_the_floor_under_the_piece() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let board = GameBoard::new(rng, 1);
+
+        let sut = build_scene(&board);
+
+        let ghosts = sut.iter().filter(|block| block.size == GHOST_SCALE).collect::<Vec<_>>();
+        assert!(ghosts.iter().any(|block| block.position.y == 0.0));
+        assert!(ghosts.iter().all(|block| block.position.x >= 0.0));
+    }
+
+
+    #[test]
+    fn locked_squares_and_hold_are_drawn_with_their_color() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let mut board = GameBoard::new(rng, 1);
+        let held_color = rgb_of(board.get_current_piece().get_squares()[0].get_color());
+        let _ = board.try_swap();
+        let locked = board.get_current_piece().get_squares()[0];
+        let _ = board.try_move(MovementDirection::Top);
+        let landing = board.get_current_piece().get_squares()[0].get_position();
+        board.lock_current_piece();
+
+        let sut = build_scene(&board);
+
+        assert!(sut.iter().any(|block| block.position.x == landing.x as f32 &&
+            block.position.y == landing.y as f32 && block.color == rgb_of(locked.get_color())));
+        assert!(sut.iter().any(|block| block.position.x < -1.0 && block.color == held_color));
+    }
+}
@@ -0,0 +1,269 @@
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::gameboard::LineClear;
+
+// The last value, for the 5 lines pentominoes can clear, was never in the originals
+const NES_CLEAR_SCORES: [u32; 5] = [40, 100, 300, 1200, 3000];
+const SEGA_CLEAR_SCORES: [u32; 5] = [100, 400, 900, 2000, 3500];
+// Sega multiplies by the level pair, capped from the ninth level on
+const SEGA_MAX_MULTIPLIER: u32 = 5;
+
+const GUIDELINE_CLEAR_SCORES: [u32; 5] = [0, 100, 300, 500, 800];
+const GUIDELINE_T_SPIN_SCORES: [u32; 4] = [400, 800, 1200, 1600];
+const GUIDELINE_PERFECT_CLEAR_SCORES: [u32; 4] = [800, 1200, 1800, 2000];
+const GUIDELINE_COMBO_SCORE: u32 = 50;
+
+pub trait ScoringSystem {
+    // Called on every lock, clear being None when no line was cleared and no T-spin made.
+    // The level is the one of the board, starting at 1
+    fn score_lock(&mut self, clear: Option<&LineClear>, level: u32) -> u32;
+
+    fn score_soft_drop(&self, _rows: u32) -> u32 {
+        0
+    }
+
+    fn score_hard_drop(&self, _rows: u32) -> u32 {
+        0
+    }
+}
+
+#[derive(EnumIter, Copy, Clone, PartialEq, Debug)]
+pub enum ScoringKind {
+    Nes,
+    Bps,
+    Sega,
+    Guideline
+}
+
+impl ScoringKind {
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            ScoringKind::Nes => "nes",
+            ScoringKind::Bps => "bps",
+            ScoringKind::Sega => "sega",
+            ScoringKind::Guideline => "guideline"
+        }
+    }
+
+
+    pub fn from_name(name: &str) -> Option<ScoringKind> {
+        ScoringKind::iter().find(|kind| kind.get_name() == name)
+    }
+
+
+    pub fn create(&self) -> Box<dyn ScoringSystem> {
+        match self {
+            ScoringKind::Nes => Box::new(NesScoring),
+            ScoringKind::Bps => Box::new(BpsScoring),
+            ScoringKind::Sega => Box::new(SegaScoring),
+            ScoringKind::Guideline => Box::new(GuidelineScoring::new())
+        }
+    }
+}
+
+
+fn clear_score(scores: &[u32; 5], clear: Option<&LineClear>) -> u32 {
+    match clear.map(|clear| clear.lines) {
+        Some(lines @ 1..) => scores[usize::min(lines as usize, scores.len()) - 1],
+        _ => 0
+    }
+}
+
+
+// Nintendo's NES Tetris, every pushed down row is worth a point. Its levels start at 0, so the
+// multiplier is the level of the board
+pub struct NesScoring;
+
+impl ScoringSystem for NesScoring {
+
+    fn score_lock(&mut self, clear: Option<&LineClear>, level: u32) -> u32 {
+        clear_score(&NES_CLEAR_SCORES, clear) * level
+    }
+
+
+    fn score_soft_drop(&self, rows: u32) -> u32 {
+        rows
+    }
+}
+
+
+// BPS' Tetris for the Famicom, the same whatever the level
+pub struct BpsScoring;
+
+impl ScoringSystem for BpsScoring {
+
+    fn score_lock(&mut self, clear: Option<&LineClear>, _level: u32) -> u32 {
+        clear_score(&NES_CLEAR_SCORES, clear)
+    }
+}
+
+
+// Sega's arcade Tetris, the multiplier grows every two levels, counted from 0
+pub struct SegaScoring;
+
+impl ScoringSystem for SegaScoring {
+
+    fn score_lock(&mut self, clear: Option<&LineClear>, level: u32) -> u32 {
+        let multiplier = u32::min(level.saturating_sub(1) / 2 + 1, SEGA_MAX_MULTIPLIER);
+        clear_score(&SEGA_CLEAR_SCORES, clear) * multiplier
+    }
+}
+
+
+// Modern guideline with T-spins, back-to-back, combos and perfect clears
+pub struct GuidelineScoring {
+    combo: Option<u32>
+}
+
+impl GuidelineScoring {
+
+    pub fn new() -> Self {
+        GuidelineScoring {
+            combo: None
+        }
+    }
+}
+
+impl ScoringSystem for GuidelineScoring {
+
+    fn score_lock(&mut self, clear: Option<&LineClear>, level: u32) -> u32 {
+        let clear = match clear {
+            Some(clear) => clear,
+            None => {
+                self.combo = None;
+                return 0;
+            }
+        };
+        let lines = usize::min(clear.lines as usize, 4);
+
+        let mut score = match clear.t_spin {
+            true => GUIDELINE_T_SPIN_SCORES[usize::min(lines, 3)],
+            false => GUIDELINE_CLEAR_SCORES[lines]
+        };
+        if lines == 0 {
+            self.combo = None;
+            return score * level;
+        }
+
+        // Tetrises and T-spins in a row are worth half more
+        if clear.back_to_back {
+            score += score / 2;
+        }
+
+        self.combo = Some(self.combo.map_or(0, |combo| combo + 1));
+        score += GUIDELINE_COMBO_SCORE * self.combo.unwrap();
+
+        if clear.perfect_clear {
+            score += GUIDELINE_PERFECT_CLEAR_SCORES[lines - 1];
+        }
+        score * level
+    }
+
+
+    fn score_soft_drop(&self, rows: u32) -> u32 {
+        rows
+    }
+
+
+    fn score_hard_drop(&self, rows: u32) -> u32 {
+        2 * rows
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use strum::IntoEnumIterator;
+
+    use crate::gameboard::LineClear;
+
+    use super::{ScoringKind, ScoringSystem, NesScoring, BpsScoring, SegaScoring, GuidelineScoring};
+
+    fn lines(lines: u32) -> Option<LineClear> {
+        Some(LineClear { lines, t_spin: false, perfect_clear: false, back_to_back: false })
+    }
+
+    fn t_spin(lines: u32) -> Option<LineClear> {
+        Some(LineClear { lines, t_spin: true, perfect_clear: false, back_to_back: false })
+    }
+
+    #[test]
+    fn every_scoring_kind_is_found_back_from_its_name() {
+        for kind in ScoringKind::iter() {
+            assert_eq!(ScoringKind::from_name(kind.get_name()), Some(kind));
+        }
+    }
+
+
+    #[test]
+    fn nes_scores_grow_with_the_level() {
+        let mut sut = NesScoring;
+
+        assert_eq!(sut.score_lock(lines(1).as_ref(), 1), 40);
+        assert_eq!(sut.score_lock(lines(4).as_ref(), 1), 1200);
+        assert_eq!(sut.score_lock(lines(2).as_ref(), 10), 1000);
+        assert_eq!(sut.score_lock(None, 9), 0);
+        assert_eq!(sut.score_hard_drop(10), 0);
+    }
+
+
+    #[test]
+    fn bps_scores_ignore_the_level() {
+        let mut sut = BpsScoring;
+
+        assert_eq!(sut.score_lock(lines(3).as_ref(), 1), 300);
+        assert_eq!(sut.score_lock(lines(3).as_ref(), 15), 300);
+    }
+
+
+    #[test]
+    fn sega_multiplier_grows_every_two_levels_up_to_five() {
+        let mut sut = SegaScoring;
+
+        assert_eq!(sut.score_lock(lines(4).as_ref(), 1), 2000);
+        assert_eq!(sut.score_lock(lines(4).as_ref(), 2), 2000);
+        assert_eq!(sut.score_lock(lines(4).as_ref(), 5), 6000);
+        assert_eq!(sut.score_lock(lines(1).as_ref(), 30), 500);
+    }
+
+
+    #[test]
+    fn pentomino_clears_of_5_lines_score_more_than_a_tetris() {
+        assert_eq!(NesScoring.score_lock(lines(5).as_ref(), 1), 3000);
+        assert_eq!(BpsScoring.score_lock(lines(5).as_ref(), 1), 3000);
+        assert_eq!(SegaScoring.score_lock(lines(5).as_ref(), 1), 3500);
+    }
+
+
+    #[test]
+    fn guideline_rewards_back_to_back_tetrises_and_combos() {
+        let mut sut = GuidelineScoring::new();
+
+        let back_to_back = LineClear { back_to_back: true, ..lines(4).unwrap() };
+
+        assert_eq!(sut.score_lock(lines(4).as_ref(), 1), 800);
+        // Back-to-back bonus plus a first combo
+        assert_eq!(sut.score_lock(Some(&back_to_back), 1), 1200 + 50);
+        // A single breaks the back-to-back but keeps the combo going
+        assert_eq!(sut.score_lock(lines(1).as_ref(), 1), 100 + 100);
+        assert_eq!(sut.score_lock(None, 1), 0);
+        assert_eq!(sut.score_lock(lines(4).as_ref(), 2), 1600);
+    }
+
+
+    #[test]
+    fn guideline_t_spins_and_perfect_clears_are_worth_more() {
+        let mut sut = GuidelineScoring::new();
+
+        assert_eq!(sut.score_lock(t_spin(0).as_ref(), 1), 400);
+        assert_eq!(sut.score_lock(t_spin(2).as_ref(), 1), 1200);
+
+        let mut sut = GuidelineScoring::new();
+        let perfect_clear = LineClear { lines: 2, t_spin: false, perfect_clear: true, back_to_back: false };
+        assert_eq!(sut.score_lock(Some(&perfect_clear), 3), (300 + 1200) * 3);
+        assert_eq!(sut.score_soft_drop(3), 3);
+        assert_eq!(sut.score_hard_drop(3), 6);
+    }
+}
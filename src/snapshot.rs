@@ -0,0 +1,253 @@
+use std::{fmt::Display, fs::File, io::BufWriter, path::{Path, PathBuf}};
+
+use nalgebra::Vector3;
+
+use crate::{gameboard::GameBoard, graphics::Visualizer, game_stats::GameStats, scene::{build_scene, SceneBlock}};
+
+pub const DEFAULT_CELL_PIXELS: u32 = 24;
+const BACKGROUND_COLOR: [u8; 3] = [13, 13, 26];
+const WELL_COLOR: [u8; 3] = [24, 24, 40];
+const GRID_COLOR: [u8; 3] = [40, 40, 60];
+// Blank cells kept around the drawing
+const MARGIN: f32 = 1.0;
+
+#[derive(Debug)]
+pub struct SnapshotError(String);
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+
+fn to_pixel_color(color: [f32; 3]) -> [u8; 3] {
+    color.map(|component| (component.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+
+// Area covered by blocks, in scene units
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SceneBounds {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32
+}
+
+impl SceneBounds {
+
+    pub fn of_blocks(blocks: &[SceneBlock]) -> SceneBounds {
+        SceneBounds {
+            left: blocks.iter().map(|block| block.position.x - block.size / 2.0).fold(f32::MAX, f32::min),
+            right: blocks.iter().map(|block| block.position.x + block.size / 2.0).fold(f32::MIN, f32::max),
+            bottom: blocks.iter().map(|block| block.position.y - block.size / 2.0).fold(f32::MAX, f32::min),
+            top: blocks.iter().map(|block| block.position.y + block.size / 2.0).fold(f32::MIN, f32::max)
+        }
+    }
+
+
+    pub fn union(&self, other: &SceneBounds) -> SceneBounds {
+        SceneBounds {
+            left: f32::min(self.left, other.left),
+            right: f32::max(self.right, other.right),
+            bottom: f32::min(self.bottom, other.bottom),
+            top: f32::max(self.top, other.top)
+        }
+    }
+}
+
+
+// RGB picture of a board, drawn from the same scene as the 3D renderer seen from the front
+#[derive(Clone, PartialEq, Debug)]
+pub struct Snapshot {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>
+}
+
+impl Snapshot {
+
+    pub fn of_board(board: &GameBoard, cell_pixels: u32) -> Snapshot {
+        let blocks = build_scene(board);
+        Snapshot::draw(board, &blocks, SceneBounds::of_blocks(&blocks), cell_pixels)
+    }
+
+
+    // Board drawn on a picture covering the given bounds, so pictures of different boards can match
+    pub fn within(board: &GameBoard, bounds: SceneBounds, cell_pixels: u32) -> Snapshot {
+        Snapshot::draw(board, &build_scene(board), bounds, cell_pixels)
+    }
+
+
+    fn draw(board: &GameBoard, blocks: &[SceneBlock], bounds: SceneBounds, cell_pixels: u32) -> Snapshot {
+        let dimensions = board.get_dimensions();
+        let size = dimensions.cell_size as f32;
+
+        // Every scene unit is cell_pixels wide, the scene y axis going up
+        let left = bounds.left - MARGIN;
+        let top = bounds.top + MARGIN;
+        let (width, height) = Snapshot::get_size(bounds, cell_pixels);
+
+        let mut snapshot = Snapshot {
+            width,
+            height,
+            pixels: BACKGROUND_COLOR.repeat((width * height) as usize)
+        };
+        let mut draw = |block: &SceneBlock, color: [u8; 3], inset: u32| {
+            let x = ((block.position.x - block.size / 2.0 - left) * cell_pixels as f32).round() as u32;
+            let y = ((top - block.position.y - block.size / 2.0) * cell_pixels as f32).round() as u32;
+            let side = (block.size * cell_pixels as f32).round() as u32;
+            snapshot.fill(x + inset, y + inset, side.saturating_sub(2 * inset), color);
+        };
+
+        // Empty cells of the well, the grid showing between them
+        for x in 0..dimensions.width {
+            for y in 0..dimensions.height {
+                let cell = SceneBlock {
+                    position: Vector3::new(x as f32 * size, y as f32 * size, 0.0),
+                    size,
+                    color: [0.0; 3]
+                };
+                draw(&cell, GRID_COLOR, 0);
+                draw(&cell, WELL_COLOR, 1);
+            }
+        }
+        for block in blocks.iter() {
+            draw(block, to_pixel_color(block.color), 1);
+        }
+        snapshot
+    }
+
+
+    // Size in pixels of the pictures covering the bounds
+    pub fn get_size(bounds: SceneBounds, cell_pixels: u32) -> (u32, u32) {
+        (
+            ((bounds.right - bounds.left + 2.0 * MARGIN) * cell_pixels as f32).round() as u32,
+            ((bounds.top - bounds.bottom + 2.0 * MARGIN) * cell_pixels as f32).round() as u32
+        )
+    }
+
+
+    pub fn get_pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+
+    // Square with its top left corner at the given pixel, cut at the borders of the picture
+    fn fill(&mut self, x: u32, y: u32, side: u32, color: [u8; 3]) {
+        for row in y..u32::min(y + side, self.height) {
+            for column in x..u32::min(x + side, self.width) {
+                let index = 3 * (row * self.width + column) as usize;
+                self.pixels[index..index + 3].copy_from_slice(&color);
+            }
+        }
+    }
+
+
+    pub fn save_png(&self, path: &Path) -> Result<(), SnapshotError> {
+        let error = |error: &dyn Display| SnapshotError(format!("Cannot write {} : {}", path.display(), error));
+        let file = File::create(path).map_err(|e| error(&e))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::RGB);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| error(&e))?;
+        writer.write_image_data(&self.pixels).map_err(|e| error(&e))
+    }
+}
+
+
+// Writes every displayed board to the same PNG file, needing no window nor terminal
+pub struct PngVisualizer {
+    output: PathBuf,
+    cell_pixels: u32
+}
+
+impl PngVisualizer {
+
+    pub fn new(output: PathBuf) -> Self {
+        PngVisualizer {
+            output,
+            cell_pixels: DEFAULT_CELL_PIXELS
+        }
+    }
+
+
+    pub fn save(&self, board: &GameBoard) -> Result<(), SnapshotError> {
+        Snapshot::of_board(board, self.cell_pixels).save_png(&self.output)
+    }
+}
+
+impl Visualizer for PngVisualizer {
+
+    fn display(&mut self, board: &GameBoard, _stats: &GameStats) {
+        if let Err(error) = self.save(board) {
+            eprintln!("{}", error);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{gameboard::{GameBoard, MovementDirection}, scene::{build_scene, rgb_of, WALL_COLOR}};
+
+    use super::{Snapshot, PngVisualizer, to_pixel_color, BACKGROUND_COLOR};
+
+    fn pixel(snapshot: &Snapshot, x: u32, y: u32) -> [u8; 3] {
+        let index = 3 * (y * snapshot.width + x) as usize;
+        [snapshot.pixels[index], snapshot.pixels[index + 1], snapshot.pixels[index + 2]]
+    }
+
+    #[test]
+    fn snapshot_covers_the_scene_and_its_margin() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let board = GameBoard::new(rng, 1);
+        let blocks = build_scene(&board);
+        let left = blocks.iter().map(|block| block.position.x).fold(f32::MAX, f32::min);
+        let right = blocks.iter().map(|block| block.position.x).fold(f32::MIN, f32::max);
+
+        let sut = Snapshot::of_board(&board, 10);
+
+        // Outermost block centers, half a block each side and a margin cell each side
+        assert_eq!(sut.width, ((right - left + 3.0) * 10.0) as u32);
+        assert_eq!(pixel(&sut, 0, 0), BACKGROUND_COLOR);
+    }
+
+
+    #[test]
+    fn locked_squares_are_drawn_with_their_color() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let mut board = GameBoard::new(rng, 1);
+        let locked = board.get_current_piece().get_squares()[0];
+        let _ = board.try_move(MovementDirection::Top);
+        let landing = board.get_current_piece().get_squares()[0].get_position();
+        board.lock_current_piece();
+
+        let sut = Snapshot::of_board(&board, 10);
+
+        // The left wall is the leftmost column, right after the margin
+        let x = (landing.x as u32 + 2) * 10 + 5;
+        let y = sut.height - (landing.y as u32 + 2) * 10 - 5;
+        assert_eq!(pixel(&sut, x, y), to_pixel_color(rgb_of(locked.get_color())));
+        assert_eq!(pixel(&sut, 15, y), to_pixel_color(WALL_COLOR));
+    }
+
+
+    #[test]
+    fn visualizer_writes_a_png_file() {
+        let rng = ChaCha8Rng::seed_from_u64(1);
+        let board = GameBoard::new(rng, 1);
+        let output = std::env::temp_dir().join("tetrust_snapshot_test.png");
+        let sut = PngVisualizer::new(output.clone());
+
+        sut.save(&board).unwrap();
+
+        let content = std::fs::read(&output).unwrap();
+        assert_eq!(&content[1..4], b"PNG");
+        let _ = std::fs::remove_file(output);
+    }
+}
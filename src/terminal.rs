@@ -0,0 +1,73 @@
+use std::io::Write;
+
+use console::Term;
+
+// Terminal settings found when the game started, None when the standard input is not a terminal
+#[cfg(unix)]
+type Settings = Option<libc::termios>;
+#[cfg(not(unix))]
+type Settings = ();
+
+/*
+ * Keeps the terminal set up for the game while it lives : cursor hidden, typed keys not echoed, and
+ * Ctrl-C and Ctrl-S read as keys. Everything is put back when it is dropped, or before a panic is reported.
+ */
+pub struct TerminalGuard {
+    settings: Settings
+}
+
+impl TerminalGuard {
+
+    pub fn new() -> Self {
+        let settings = set_up();
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore(settings);
+            previous_hook(info);
+        }));
+        let _ = Term::stdout().hide_cursor();
+        TerminalGuard { settings }
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore(self.settings);
+    }
+}
+
+
+#[cfg(unix)]
+fn set_up() -> Settings {
+    unsafe {
+        let mut settings = std::mem::MaybeUninit::<libc::termios>::uninit();
+        if libc::isatty(libc::STDIN_FILENO) != 1 || libc::tcgetattr(libc::STDIN_FILENO, settings.as_mut_ptr()) != 0 {
+            return None;
+        }
+        let original = settings.assume_init();
+        let mut game = original;
+        game.c_lflag &= !(libc::ECHO | libc::ICANON | libc::ISIG);
+        // Ctrl-S would otherwise freeze the output until Ctrl-Q
+        game.c_iflag &= !libc::IXON;
+        libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &game);
+        Some(original)
+    }
+}
+
+
+#[cfg(not(unix))]
+fn set_up() -> Settings {}
+
+
+fn restore(settings: Settings) {
+    #[cfg(unix)]
+    if let Some(original) = settings {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &original) };
+    }
+    #[cfg(not(unix))]
+    let () = settings;
+
+    print!("\x1B[0m\x1B[2J\x1B[H");
+    let _ = Term::stdout().show_cursor();
+    let _ = std::io::stdout().flush();
+}
@@ -0,0 +1,354 @@
+use std::{fmt::Display, fs, path::Path};
+
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::piece::Color;
+
+// Themes shipped with the game, any other name being read as a theme file
+const BUILT_IN_THEMES: [(&str, &str); 3] = [
+    ("default", include_str!("../themes/default.txt")),
+    ("high-contrast", include_str!("../themes/high-contrast.txt")),
+    ("ascii", include_str!("../themes/ascii.txt"))
+];
+// Squares and empty cells are two terminal columns wide
+const CELL_WIDTH: usize = 2;
+// Squares lighter than this get a black foreground, the others a white one
+const LIGHT_LUMINANCE: f32 = 140.0;
+
+// Backgrounds of the palettes, the pairs each kind of colorblindness confuses being told
+// apart by their lightness as much as by their hue
+const DEUTERANOPIA_PALETTE: [(Color, [u8; 3]); 8] = [
+    (Color::Cyan, [0x56, 0xb4, 0xe9]),
+    (Color::DarkBlue, [0x00, 0x48, 0x8a]),
+    (Color::Orange, [0xe6, 0x9f, 0x00]),
+    (Color::Yellow, [0xf0, 0xe4, 0x42]),
+    (Color::Green, [0x00, 0x9e, 0x73]),
+    (Color::Purple, [0xcc, 0x79, 0xa7]),
+    (Color::Red, [0x8c, 0x2d, 0x04]),
+    (Color::Gray, [0xbb, 0xbb, 0xbb])
+];
+const PROTANOPIA_PALETTE: [(Color, [u8; 3]); 8] = [
+    (Color::Cyan, [0x66, 0xcc, 0xee]),
+    (Color::DarkBlue, [0x22, 0x44, 0xaa]),
+    (Color::Orange, [0xff, 0xb0, 0x00]),
+    (Color::Yellow, [0xff, 0xf3, 0x9b]),
+    (Color::Green, [0x22, 0x88, 0x33]),
+    (Color::Purple, [0x88, 0x22, 0x55]),
+    (Color::Red, [0xdc, 0x26, 0x7f]),
+    (Color::Gray, [0xcc, 0xcc, 0xcc])
+];
+const TRITANOPIA_PALETTE: [(Color, [u8; 3]); 8] = [
+    (Color::Cyan, [0x00, 0xcc, 0xcc]),
+    (Color::DarkBlue, [0x1b, 0x1b, 0x7a]),
+    (Color::Orange, [0xff, 0x8c, 0x69]),
+    (Color::Yellow, [0xff, 0xd9, 0xe6]),
+    (Color::Green, [0x00, 0x6d, 0x5b]),
+    (Color::Purple, [0x9e, 0x3c, 0xbf]),
+    (Color::Red, [0xd7, 0x19, 0x1c]),
+    (Color::Gray, [0x9a, 0x9a, 0x9a])
+];
+
+#[derive(Debug)]
+pub struct ThemeError(String);
+
+impl Display for ThemeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+
+// Colors a terminal can show, most of them handling 256 and the recent ones any RGB value
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ColorDepth {
+    TrueColor,
+    Palette256
+}
+
+impl ColorDepth {
+
+    pub fn detect() -> ColorDepth {
+        match std::env::var("COLORTERM").as_deref() {
+            Ok("truecolor" | "24bit") => ColorDepth::TrueColor,
+            _ => ColorDepth::Palette256
+        }
+    }
+}
+
+
+// Square colors replacing the ones of any theme for players with colorblindness
+#[derive(EnumIter, Copy, Clone, PartialEq, Debug)]
+pub enum Palette {
+    Deuteranopia,
+    Protanopia,
+    Tritanopia
+}
+
+impl Palette {
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Palette::Deuteranopia => "deuteranopia",
+            Palette::Protanopia => "protanopia",
+            Palette::Tritanopia => "tritanopia"
+        }
+    }
+
+
+    pub fn from_name(name: &str) -> Option<Palette> {
+        Palette::iter().find(|palette| palette.get_name() == name)
+    }
+
+
+    fn get_backgrounds(&self) -> &'static [(Color, [u8; 3]); 8] {
+        match self {
+            Palette::Deuteranopia => &DEUTERANOPIA_PALETTE,
+            Palette::Protanopia => &PROTANOPIA_PALETTE,
+            Palette::Tritanopia => &TRITANOPIA_PALETTE
+        }
+    }
+
+
+    // Glyphs of the theme are kept, only its square colors change
+    pub fn apply(&self, theme: &Theme) -> Theme {
+        let squares = self.get_backgrounds().iter().map(|(color, background)| {
+            let [red, green, blue] = background.map(|component| component as f32);
+            let luminance = 0.299 * red + 0.587 * green + 0.114 * blue;
+            let foreground = if luminance > LIGHT_LUMINANCE { [0, 0, 0] } else { [255, 255, 255] };
+            (*color, SquareStyle { foreground, background: *background })
+        });
+        Theme {
+            squares: squares.collect(),
+            ..theme.clone()
+        }
+    }
+}
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SquareStyle {
+    pub foreground: [u8; 3],
+    pub background: [u8; 3]
+}
+
+/*
+ * Themes are text files of "key: value" lines, "#" starting a comment, every key being needed :
+ *
+ *   square: ⟦⟧                 (two columns wide, e.g. two characters or one wide character)
+ *   empty: " ."                (two columns wide too, quotes keeping the spaces)
+ *   left-border: |
+ *   right-border: |
+ *   bottom-border: -
+ *   cyan: #000000 on #00cdcd   (foreground on background, one line per color)
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct Theme {
+    squares: Vec<(Color, SquareStyle)>,
+    square_glyphs: String,
+    empty_glyphs: String,
+    left_border: String,
+    right_border: String,
+    bottom_border: String
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::from_name("default").unwrap()
+    }
+}
+
+impl Theme {
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        BUILT_IN_THEMES.iter()
+            .find(|(built_in, _)| *built_in == name)
+            .map(|(_, text)| Theme::parse(text).expect("Built-in themes are valid"))
+    }
+
+
+    pub fn get_built_in_names() -> Vec<&'static str> {
+        BUILT_IN_THEMES.iter().map(|(name, _)| *name).collect()
+    }
+
+
+    // None for themes loaded from a file
+    pub fn get_name(&self) -> Option<&'static str> {
+        Theme::get_built_in_names().into_iter().find(|name| Theme::from_name(name).as_ref() == Some(self))
+    }
+
+
+    pub fn load(path: &Path) -> Result<Theme, ThemeError> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| ThemeError(format!("Cannot read {} : {}", path.display(), error)))?;
+        Theme::parse(&text)
+    }
+
+
+    pub fn parse(text: &str) -> Result<Theme, ThemeError> {
+        let mut squares = vec![];
+        let mut glyphs: [Option<String>; 5] = Default::default();
+        let glyph_keys = ["square", "empty", "left-border", "right-border", "bottom-border"];
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let (key, value) = line.split_once(':')
+                .ok_or(ThemeError(format!("Expected \"key: value\", found : {}", line)))?;
+            let (key, value) = (key.trim(), value.trim());
+            let value = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')).unwrap_or(value);
+
+            if let Some(index) = glyph_keys.iter().position(|glyph_key| *glyph_key == key) {
+                // Glyphs are measured in terminal columns, wide characters taking two
+                let expected = if index < 2 { CELL_WIDTH } else { 1 };
+                if console::measure_text_width(value) != expected {
+                    return Err(ThemeError(format!("{} expects {} column(s), found : {}", key, expected, value)));
+                }
+                glyphs[index] = Some(value.to_string());
+                continue;
+            }
+
+            let color = Color::from_name(key).ok_or(ThemeError(format!("Unknown key : {}", key)))?;
+            squares.retain(|(defined, _)| *defined != color);
+            squares.push((color, Theme::parse_style(value)?));
+        }
+
+        if let Some(color) = Color::iter().find(|color| squares.iter().all(|(defined, _)| defined != color)) {
+            return Err(ThemeError(format!("Missing color : {}", color.get_name())));
+        }
+        let [square_glyphs, empty_glyphs, left_border, right_border, bottom_border] = glyphs;
+        let missing = |index: usize| ThemeError(format!("Missing key : {}", glyph_keys[index]));
+        Ok(Theme {
+            squares,
+            square_glyphs: square_glyphs.ok_or_else(|| missing(0))?,
+            empty_glyphs: empty_glyphs.ok_or_else(|| missing(1))?,
+            left_border: left_border.ok_or_else(|| missing(2))?,
+            right_border: right_border.ok_or_else(|| missing(3))?,
+            bottom_border: bottom_border.ok_or_else(|| missing(4))?
+        })
+    }
+
+
+    // "#rrggbb on #rrggbb"
+    fn parse_style(value: &str) -> Result<SquareStyle, ThemeError> {
+        let parse_rgb = |text: &str| -> Option<[u8; 3]> {
+            let hex = text.trim().strip_prefix('#').filter(|hex| hex.len() == 6)?;
+            let component = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+            Some([component(0)?, component(2)?, component(4)?])
+        };
+        value.split_once(" on ")
+            .and_then(|(foreground, background)| Some(SquareStyle {
+                foreground: parse_rgb(foreground)?,
+                background: parse_rgb(background)?
+            }))
+            .ok_or(ThemeError(format!("Expected \"#rrggbb on #rrggbb\", found : {}", value)))
+    }
+
+
+    pub fn get_square_style(&self, color: Color) -> SquareStyle {
+        self.squares.iter().find(|(defined, _)| *defined == color).unwrap().1
+    }
+
+
+    pub fn get_square_glyphs(&self) -> &str {
+        &self.square_glyphs
+    }
+
+
+    pub fn get_empty_glyphs(&self) -> &str {
+        &self.empty_glyphs
+    }
+
+
+    pub fn get_left_border(&self) -> &str {
+        &self.left_border
+    }
+
+
+    pub fn get_right_border(&self) -> &str {
+        &self.right_border
+    }
+
+
+    pub fn get_bottom_border(&self) -> &str {
+        &self.bottom_border
+    }
+
+
+    pub fn paint_square(&self, color: Color, depth: ColorDepth) -> String {
+        self.paint(color, depth, &self.square_glyphs)
+    }
+
+
+    // Glyphs in the colors of a square with their escape codes, reset at the end
+    pub fn paint(&self, color: Color, depth: ColorDepth, glyphs: &str) -> String {
+        let style = self.get_square_style(color);
+        let codes = match depth {
+            ColorDepth::TrueColor => {
+                let [red, green, blue] = style.foreground;
+                let [back_red, back_green, back_blue] = style.background;
+                format!("38;2;{};{};{};48;2;{};{};{}", red, green, blue, back_red, back_green, back_blue)
+            },
+            ColorDepth::Palette256 => format!("38;5;{};48;5;{}", to_palette_256(style.foreground), to_palette_256(style.background))
+        };
+        format!("\x1B[{}m{}\x1B[0m", codes, glyphs)
+    }
+}
+
+
+// Nearest color of the 6x6x6 cube of the 256 color palette
+fn to_palette_256(rgb: [u8; 3]) -> u8 {
+    let [red, green, blue] = rgb.map(|component| ((component as u16 * 5 + 127) / 255) as u8);
+    16 + 36 * red + 6 * green + blue
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::piece::Color;
+
+    use super::{Theme, ColorDepth, Palette, BUILT_IN_THEMES, to_palette_256};
+
+    #[test]
+    fn built_in_themes_are_valid() {
+        for (name, _) in BUILT_IN_THEMES {
+            assert_eq!(Theme::from_name(name).unwrap().get_name(), Some(name));
+        }
+        assert_eq!(Theme::from_name("ascii").unwrap().get_empty_glyphs(), " .");
+    }
+
+
+    #[test]
+    fn squares_are_painted_in_truecolor_or_with_the_palette() {
+        let sut = Theme::from_name("high-contrast").unwrap();
+
+        assert_eq!(sut.paint_square(Color::Red, ColorDepth::TrueColor), "\x1B[38;2;255;255;255;48;2;255;0;0m⟦⟧\x1B[0m");
+        assert_eq!(sut.paint_square(Color::Red, ColorDepth::Palette256), "\x1B[38;5;231;48;5;196m⟦⟧\x1B[0m");
+        assert_eq!(to_palette_256([0, 0, 0]), 16);
+    }
+
+
+    #[test]
+    fn incomplete_or_malformed_themes_are_errors() {
+        let ascii = BUILT_IN_THEMES[2].1;
+
+        assert!(Theme::parse(&ascii.replace("gray: #000000 on #e5e5e5", "")).is_err());
+        assert!(Theme::parse(&ascii.replace("square: []", "square: [[]")).is_err());
+        assert!(Theme::parse(&ascii.replace("#00cdcd", "cyan")).is_err());
+        // A wide character fills a whole cell on its own
+        assert!(Theme::parse(&ascii.replace("square: []", "square: 口")).is_ok());
+        assert!(Theme::parse(&ascii.replace("square: []", "square: 口口")).is_err());
+    }
+
+
+    #[test]
+    fn palettes_replace_the_colors_and_keep_the_glyphs() {
+        let theme = Theme::from_name("ascii").unwrap();
+
+        let sut = Palette::from_name("deuteranopia").unwrap().apply(&theme);
+
+        assert_eq!(sut.paint_square(Color::Red, ColorDepth::TrueColor), "\x1B[38;2;255;255;255;48;2;140;45;4m[]\x1B[0m");
+        assert_eq!(sut.get_square_style(Color::Orange).background, [0xe6, 0x9f, 0x00]);
+        // Light squares are written in black, dark ones in white
+        assert_eq!(sut.get_square_style(Color::Yellow).foreground, [0, 0, 0]);
+        assert_eq!(sut.get_square_style(Color::DarkBlue).foreground, [255, 255, 255]);
+    }
+}